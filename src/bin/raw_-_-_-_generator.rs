@@ -4,6 +4,7 @@ use rand::rngs::OsRng;
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use Efficient_ZKP_for_Affine_forms::curve25519::canonical;
 use Efficient_ZKP_for_Affine_forms::curve25519::scalar::Scalar;
 use Efficient_ZKP_for_Affine_forms::curve25519::scalar_math;
 
@@ -18,16 +19,20 @@ fn random_in_1e9<Rng: RngCore + CryptoRng>(rng: &mut Rng) -> u64 {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Raw {
+    #[serde(with = "canonical::decimal")]
     m_matric: Vec<Vec<Scalar>>,
+    #[serde(with = "canonical::decimal")]
     b_vec: Vec<Scalar>,
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Dispose {
+    #[serde(with = "canonical::decimal")]
     l_affine_vec: Vec<Scalar>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct X {
+    #[serde(with = "canonical::decimal")]
     x_vec: Vec<Scalar>,
 }
 
@@ -36,12 +41,12 @@ fn write_to_file<T: Serialize>(path: String, object: T) {
 }
 
 pub fn generator(suffix_path: String) {
-    let infix_path = "./random_data/1e9_100000_50".to_owned();
+    let infix_path = "./random_data/1e9_100000_50";
 
     // Scalar range in [0,1e9), and n range in {10},s range in {5}
 
-    let raw_file_path = infix_path.to_owned() + &"/raw_".to_owned() + &suffix_path;
-    let private_file_path = infix_path.to_owned() + &"/private_".to_owned() + &suffix_path;
+    let raw_file_path = infix_path.to_owned() + "/raw_" + &suffix_path;
+    let private_file_path = infix_path.to_owned() + "/private_" + &suffix_path;
     //? rust . + & + & + &
 
     let mut csprng: OsRng = OsRng;
@@ -52,25 +57,13 @@ pub fn generator(suffix_path: String) {
     let mut m_matric: Vec<Vec<Scalar>> = Vec::new();
     let mut m_hat_matric: Vec<Vec<Scalar>> = Vec::new();
     let mut b_vec: Vec<Scalar> = Vec::new();
-    let mut x_hat_vec: Vec<Scalar> = Vec::new();
 
-    let mut lim = 1;
+    // no padding needed: the proof pads n up to a power of two internally
+    let x_vec: Vec<Scalar> = (0..n)
+        .map(|_| Scalar::from(random_in_1e9(&mut csprng)))
+        .collect();
 
-    while lim < n + 2 {
-        lim = lim * 2;
-    }
-
-    for _ in 0..n {
-        x_hat_vec.push(Scalar::from(random_in_1e9(&mut csprng)));
-    }
-
-    for _ in n..lim - 2 {
-        x_hat_vec.push(Scalar::zero());
-    }
-
-    assert_eq!(x_hat_vec.len(), lim - 2);
-
-    let x_vec = x_hat_vec.clone();
+    let mut x_hat_vec = x_vec.clone();
     x_hat_vec.push(-Scalar::one());
 
     for _ in 0..s {
@@ -78,12 +71,6 @@ pub fn generator(suffix_path: String) {
             .map(|_| Scalar::from(random_in_1e9(&mut csprng)))
             .collect();
 
-        for _ in n..lim - 2 {
-            tmp.push(Scalar::zero());
-        }
-
-        assert_eq!(tmp.len(), lim - 2);
-
         m_matric.push(tmp.clone());
 
         //make $<L_i, \vec{x}>=0$
@@ -118,6 +105,6 @@ pub fn generator(suffix_path: String) {
 
 fn main() {
     for i in 0..10 {
-        generator(i.to_string() + &".in".to_owned());
+        generator(i.to_string() + ".in");
     }
 }
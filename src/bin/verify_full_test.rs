@@ -1,91 +1,73 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
 use merlin::Transcript;
-use serde::{Deserialize, Serialize};
-use serde_json;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
 use Efficient_ZKP_for_Affine_forms::{
-    commitments::DotProductProofGens,
-    curve25519::{group::CompressedGroup, scalar::Scalar},
-    runtime,
-    transcript::AppendToTranscript,
+    commitments::{DotProductProofGens, GensCheck},
+    encoding, runtime,
+    statement_stream::{read_affine_num_cols, read_affine_statement},
     zk_protocol::pi_a_protocol::Pi_Affine_Proof,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Raw {
-    m_matric: Vec<Vec<Scalar>>,
-    b_vec: Vec<Scalar>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct X {
-    x_vec: Vec<Scalar>,
-}
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Proof_and_Commitments {
-    proof: Pi_Affine_Proof,
-    P: CompressedGroup,
-    P_hat: CompressedGroup,
-    y: Scalar,
-}
-
 fn singleton_test(suffix_path: String) {
     use std::time::Instant;
     let mut now = Instant::now();
 
-    let infix_path = "./random_data/1e9_100000_50/".to_owned();
-
-    let raw_path = infix_path.to_owned() + &"raw_".to_owned() + &suffix_path;
-    let proof_path = infix_path.to_owned() + &"proof_".to_owned() + &suffix_path;
-
-    let disk_raw: Raw = serde_json::from_str(&fs::read_to_string(raw_path).unwrap()).unwrap();
-    let disk_proof: Proof_and_Commitments =
-        serde_json::from_str(&fs::read_to_string(proof_path).unwrap()).unwrap();
-
-    runtime::print_runtime(&mut now, "", "input");
+    let infix_path = "./random_data/1e9_100000_50/";
 
-    let (mut m_matric, b_vec) = (disk_raw.m_matric, disk_raw.b_vec.clone());
-    let (proof, P, P_hat, y) = (
-        disk_proof.proof,
-        disk_proof.P,
-        disk_proof.P_hat,
-        disk_proof.y,
-    );
+    let raw_path = infix_path.to_owned() + "raw_" + &suffix_path;
+    let proof_path = infix_path.to_owned() + "proof_" + &suffix_path;
 
-    assert!(m_matric.len() > 0);
-    assert!(m_matric[0].len() > 0);
+    let disk_proof = fs::read(proof_path).unwrap();
 
-    let s = b_vec.len();
+    // n is taken from the matrix in a first pass over the raw file, which
+    // keeps only its first row
+    let n = read_affine_num_cols(BufReader::new(File::open(&raw_path).unwrap())).unwrap();
 
-    assert_eq!(m_matric.len(), s);
+    runtime::print_runtime(&mut now, "", "input");
 
-    for i in 0..s {
-        m_matric[i].push(b_vec[i]);
+    // the 32-byte commitment P followed by the encoded proof
+    let decoded = encoding::decode_with_commitment::<Pi_Affine_Proof>(&disk_proof);
+    if let Err(e) = &decoded {
+        println!("proof file rejected: {}", e);
     }
-
-    let n = m_matric[0].len();
-
-    let gens = DotProductProofGens::new(n, b"gens");
+    let (P, proof) = decoded.unwrap();
+
+    // a parameter file next to the data is used if present; it is not trusted
+    // to hold the points of its label, so every point is derived again and
+    // compared while loading it
+    let gens_path = infix_path.to_owned() + "gens.bin";
+    let gens = if Path::new(&gens_path).exists() {
+        let gens = DotProductProofGens::load(&gens_path, GensCheck::Rederive).unwrap();
+        assert_eq!(gens.label, b"gens");
+        gens
+    } else {
+        DotProductProofGens::new(n, b"gens")
+    };
 
     // Setup stage complete.
 
     let mut verifier_transcript = Transcript::new(b"Kinesis's protocol");
 
-    let P_secure = P;
-    P_secure.append_to_transcript(b"P_secure", &mut verifier_transcript);
-
-    assert!(proof
-        .verify(
-            n,
-            &gens,
-            &mut verifier_transcript,
-            &m_matric,
-            &P,
-            &y,
-            &P_hat
-        )
-        .is_ok());
+    // like the Prover, the statement (dense or sparse) is streamed from the
+    // raw file row by row, once for its digest and once rho is known, so
+    // memory stays O(n); a malformed file is rejected as a malformed statement
+    let result = proof.verify_affine_streaming(
+        n,
+        &gens,
+        &mut verifier_transcript,
+        |sink| {
+            let reader = BufReader::new(File::open(&raw_path)?);
+            read_affine_statement(reader, sink).map_err(io::Error::from)
+        },
+        &P,
+    );
+    if let Err(e) = &result {
+        println!("proof rejected: {}", e);
+    }
+    assert!(result.is_ok());
 
     runtime::print_runtime(&mut now, "", "verify");
 }
@@ -94,7 +76,7 @@ fn main() {
     let test_total = 1;
     for i in 0..test_total {
         println!("----------------------------------------singleton_test----------------------------------------");
-        singleton_test(i.to_string() + &".in".to_owned());
+        singleton_test(i.to_string() + ".in");
     }
     println!(
         "test result: ok. {} passed; 0 failed; 0 ignored; 0 measured.",
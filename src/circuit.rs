@@ -0,0 +1,177 @@
+//! Arithmetic circuits over the scalar field, as gate lists.
+//!
+//! Wires `0..num_inputs` carry the secret inputs, and gate `g` defines the
+//! wire `num_inputs + g` from earlier wires, so a circuit is evaluated in
+//! gate order and every wire is assigned exactly once. Selected wires are
+//! the public outputs. A circuit is built by calling the gate methods, which
+//! return the index of the new wire:
+//!
+//! ```
+//! use Efficient_ZKP_for_Affine_forms::circuit::Circuit;
+//! use Efficient_ZKP_for_Affine_forms::curve25519::scalar::Scalar;
+//!
+//! // x^3 + x + 5
+//! let mut circuit = Circuit::new(1);
+//! let x_2 = circuit.mul(0, 0);
+//! let x_3 = circuit.mul(x_2, 0);
+//! let sum = circuit.add(x_3, 0);
+//! let out = circuit.add_const(sum, Scalar::from(5u64));
+//! circuit.output(out);
+//!
+//! let wires = circuit.evaluate(&[Scalar::from(3u64)]);
+//! assert_eq!(circuit.outputs_of(&wires), vec![Scalar::from(35u64)]);
+//! ```
+use crate::curve25519::errors::ProofVerifyError;
+use crate::curve25519::scalar::Scalar;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use merlin::Transcript;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Gate {
+    Add(usize, usize),
+    Mul(usize, usize),
+    AddConst(usize, Scalar),
+    MulConst(usize, Scalar),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    pub num_inputs: usize,
+    pub gates: Vec<Gate>,
+    pub outputs: Vec<usize>,
+}
+
+impl Circuit {
+    pub fn new(num_inputs: usize) -> Self {
+        Circuit {
+            num_inputs,
+            gates: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, gate: Gate) -> usize {
+        self.gates.push(gate);
+        self.num_wires() - 1
+    }
+
+    pub fn add(&mut self, left: usize, right: usize) -> usize {
+        self.push(Gate::Add(left, right))
+    }
+
+    pub fn mul(&mut self, left: usize, right: usize) -> usize {
+        self.push(Gate::Mul(left, right))
+    }
+
+    pub fn add_const(&mut self, input: usize, c: Scalar) -> usize {
+        self.push(Gate::AddConst(input, c))
+    }
+
+    pub fn mul_const(&mut self, input: usize, c: Scalar) -> usize {
+        self.push(Gate::MulConst(input, c))
+    }
+
+    pub fn output(&mut self, wire: usize) {
+        self.outputs.push(wire);
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.num_inputs + self.gates.len()
+    }
+
+    /// The wires `(left, right, out)` of the multiplication gates, in order
+    pub fn mul_gates(&self) -> Vec<(usize, usize, usize)> {
+        self.gates
+            .iter()
+            .enumerate()
+            .filter_map(|(g, gate)| match gate {
+                Gate::Mul(left, right) => Some((*left, *right, self.num_inputs + g)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Checks that every gate only reads earlier wires and that the outputs
+    /// are wires of the circuit
+    pub fn check(&self) -> Result<(), ProofVerifyError> {
+        if self.num_inputs == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "circuit without inputs".to_string(),
+            ));
+        }
+        for (g, gate) in self.gates.iter().enumerate() {
+            let out = self.num_inputs + g;
+            let reads_later_wire = match gate {
+                Gate::Add(left, right) | Gate::Mul(left, right) => *left >= out || *right >= out,
+                Gate::AddConst(input, _) | Gate::MulConst(input, _) => *input >= out,
+            };
+            if reads_later_wire {
+                return Err(ProofVerifyError::MalformedStatement(format!(
+                    "gate {} reads a wire it does not follow",
+                    g
+                )));
+            }
+        }
+        if let Some(wire) = self.outputs.iter().find(|w| **w >= self.num_wires()) {
+            return Err(ProofVerifyError::MalformedStatement(format!(
+                "output wire {} does not exist",
+                wire
+            )));
+        }
+        Ok(())
+    }
+
+    /// The values of all wires for the given inputs
+    pub fn evaluate(&self, inputs: &[Scalar]) -> Vec<Scalar> {
+        assert_eq!(inputs.len(), self.num_inputs);
+        let mut wires = inputs.to_vec();
+        wires.reserve(self.gates.len());
+        for gate in self.gates.iter() {
+            let value = match gate {
+                Gate::Add(left, right) => wires[*left] + wires[*right],
+                Gate::Mul(left, right) => wires[*left] * wires[*right],
+                Gate::AddConst(input, c) => wires[*input] + c,
+                Gate::MulConst(input, c) => wires[*input] * c,
+            };
+            wires.push(value);
+        }
+        wires
+    }
+
+    pub fn outputs_of(&self, wires: &[Scalar]) -> Vec<Scalar> {
+        self.outputs.iter().map(|w| wires[*w]).collect()
+    }
+}
+
+impl AppendToTranscript for Circuit {
+    fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
+        transcript.append_message(label, b"begin_circuit");
+        transcript.append_u64(b"num_inputs", self.num_inputs as u64);
+        transcript.append_u64(b"num_gates", self.gates.len() as u64);
+        for gate in self.gates.iter() {
+            match gate {
+                Gate::Add(left, right) => {
+                    transcript.append_u64(b"add", *left as u64);
+                    transcript.append_u64(b"add", *right as u64);
+                }
+                Gate::Mul(left, right) => {
+                    transcript.append_u64(b"mul", *left as u64);
+                    transcript.append_u64(b"mul", *right as u64);
+                }
+                Gate::AddConst(input, c) => {
+                    transcript.append_u64(b"add_const", *input as u64);
+                    transcript.append_scalar(b"add_const", c);
+                }
+                Gate::MulConst(input, c) => {
+                    transcript.append_u64(b"mul_const", *input as u64);
+                    transcript.append_scalar(b"mul_const", c);
+                }
+            }
+        }
+        transcript.append_u64(b"num_outputs", self.outputs.len() as u64);
+        for wire in self.outputs.iter() {
+            transcript.append_u64(b"output", *wire as u64);
+        }
+        transcript.append_message(label, b"end_circuit");
+    }
+}
@@ -1,10 +1,56 @@
-use crate::curve25519::group::{GroupElement, VartimeMultiscalarMul, GROUP_BASEPOINT_COMPRESSED};
+pub mod pedersen;
+
+use crate::curve25519::errors::{GensFileError, ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, GroupElement, VartimeMultiscalarMul, GROUP_BASEPOINT_COMPRESSED,
+};
 use crate::curve25519::scalar::Scalar;
+use crate::encoding::{ProofReader, ProofWriter};
 use digest::{ExtendableOutput, Input};
+#[cfg(feature = "multicore")]
+use rayon::prelude::*;
 use sha3::Shake256;
+use std::fs;
 use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const GENS_MAGIC: &[u8; 4] = b"ZKGN";
+pub const GENS_VERSION: u8 = 2;
+const GENS_KIND_MULTI_COMMIT: u8 = 0;
+const GENS_KIND_DOT_PRODUCT: u8 = 1;
+const MAX_LABEL_LEN: usize = 1 << 16;
+
+/// How much of a generators file is checked when it is loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GensCheck {
+    /// The checksum must match and every point must decompress. This only
+    /// catches corruption: anyone can write a file with a valid checksum, so
+    /// it gives no assurance that the points come from the label, or that
+    /// nobody knows their discrete logs. Use it only for files you made.
+    Checksum,
+    /// Additionally, every point is derived again from the label and compared
+    Rederive,
+}
 
-#[derive(Debug)]
+// Every generator is hashed from the label, its own name and its index, so
+// G_i does not depend on how many generators are derived, and h and k are
+// independent of every G_i. A key for n generators is thus a prefix of any
+// larger key with the same label.
+fn derive_generator(label: &[u8], name: &[u8], index: u64) -> GroupElement {
+    let mut shake = Shake256::default();
+    shake.input((label.len() as u64).to_le_bytes());
+    shake.input(label);
+    shake.input(GROUP_BASEPOINT_COMPRESSED.as_bytes());
+    shake.input(name);
+    shake.input(index.to_le_bytes());
+
+    let mut uniform_bytes = [0u8; 64];
+    shake.xof_result().read_exact(&mut uniform_bytes).unwrap();
+    GroupElement::from_uniform_bytes(&uniform_bytes)
+}
+
+#[derive(Debug, Clone)]
 pub struct MultiCommitGens {
     pub n: usize,
     pub G: Vec<GroupElement>,
@@ -13,30 +59,36 @@ pub struct MultiCommitGens {
 
 impl MultiCommitGens {
     pub fn new(n: usize, label: &[u8]) -> Self {
-        let mut shake = Shake256::default();
-        shake.input(label);
-        shake.input(GROUP_BASEPOINT_COMPRESSED.as_bytes());
-
-        let mut reader = shake.xof_result();
-        let mut gens: Vec<GroupElement> = Vec::new();
-        let mut uniform_bytes = [0u8; 64];
-        for _ in 0..n + 1 {
-            reader.read_exact(&mut uniform_bytes).unwrap();
-            gens.push(GroupElement::from_uniform_bytes(&uniform_bytes));
-        }
+        MultiCommitGens::derive(n, label, b"G")
+    }
+
+    // n generators under `name`, with the blinding generator h of the label
+    fn derive(n: usize, label: &[u8], name: &[u8]) -> Self {
+        #[cfg(feature = "multicore")]
+        let G = (0..n as u64)
+            .into_par_iter()
+            .map(|i| derive_generator(label, name, i))
+            .collect();
+
+        #[cfg(not(feature = "multicore"))]
+        let G = (0..n as u64)
+            .map(|i| derive_generator(label, name, i))
+            .collect();
 
         MultiCommitGens {
             n,
-            G: gens[..n].to_vec(),
-            h: gens[n],
+            G,
+            h: derive_generator(label, b"h", 0),
         }
     }
 
-    pub fn clone(&self) -> MultiCommitGens {
+    // The first n generators together with the same blinding generator h
+    pub fn prefix(&self, n: usize) -> MultiCommitGens {
+        assert!(n <= self.n);
         MultiCommitGens {
-            n: self.n,
+            n,
+            G: self.G[..n].to_vec(),
             h: self.h,
-            G: self.G.clone(),
         }
     }
 
@@ -58,6 +110,152 @@ impl MultiCommitGens {
     }
 }
 
+// Generators file layout: GENS_MAGIC, GENS_VERSION, a kind byte telling
+// `MultiCommitGens` and `DotProductProofGens` files apart, the label (u32
+// length and bytes), n as a u32, a 32-byte SHAKE256 checksum over everything
+// else, then the points: G_0, ..., G_{n-1} and h, followed by k for
+// `DotProductProofGens`
+fn encode_gens(kind: u8, label: &[u8], n: usize, points: &[GroupElement]) -> Vec<u8> {
+    let mut header = ProofWriter::new();
+    for byte in GENS_MAGIC {
+        header.write_u8(*byte);
+    }
+    header.write_u8(GENS_VERSION);
+    header.write_u8(kind);
+    header.write_bytes(label);
+    header.write_len(n);
+    let header = header.into_bytes();
+
+    let mut encoded = ProofWriter::new();
+    for point in points {
+        encoded.write_point(&point.compress());
+    }
+    let encoded = encoded.into_bytes();
+
+    let mut bytes = header.clone();
+    bytes.extend_from_slice(&gens_checksum(&header, &encoded));
+    bytes.extend_from_slice(&encoded);
+    bytes
+}
+
+// Returns the label, n and the n + `extra` points of a file of the given kind
+fn decode_gens(
+    bytes: &[u8],
+    kind: u8,
+    extra: usize,
+) -> Result<(Vec<u8>, usize, Vec<GroupElement>), GensFileError> {
+    let mut reader = ProofReader::new(bytes);
+    if reader.take(GENS_MAGIC.len())? != GENS_MAGIC {
+        return Err(GensFileError::BadMagic);
+    }
+    let version = reader.read_u8()?;
+    if version != GENS_VERSION {
+        return Err(GensFileError::UnsupportedVersion(version));
+    }
+    let found = reader.read_u8()?;
+    if found != kind {
+        return Err(GensFileError::UnexpectedKind {
+            expected: kind,
+            found,
+        });
+    }
+    let label = reader.read_bytes(MAX_LABEL_LEN)?.to_vec();
+    let n = reader.read_len()?;
+    let header = &bytes[..bytes.len() - reader.remaining()];
+    let checksum = reader.take(32)?;
+
+    // n comes from the file and may overflow the size on 32-bit targets; a
+    // file that large could not be in memory, so it is reported as too short
+    let size = n
+        .checked_add(extra)
+        .and_then(|m| m.checked_mul(32))
+        .ok_or(ProofDecodeError::UnexpectedEnd)?;
+    let points = reader.take(size)?;
+    if reader.remaining() != 0 {
+        return Err(ProofDecodeError::TrailingBytes(reader.remaining()).into());
+    }
+    if gens_checksum(header, points) != checksum {
+        return Err(GensFileError::ChecksumMismatch);
+    }
+
+    let decompress = |(i, chunk): (usize, &[u8])| {
+        CompressedGroup::from_slice(chunk)
+            .decompress()
+            .ok_or(GensFileError::InvalidPoint(i))
+    };
+    #[cfg(feature = "multicore")]
+    let points: Result<Vec<GroupElement>, GensFileError> =
+        points.par_chunks(32).enumerate().map(decompress).collect();
+    #[cfg(not(feature = "multicore"))]
+    let points: Result<Vec<GroupElement>, GensFileError> =
+        points.chunks(32).enumerate().map(decompress).collect();
+
+    Ok((label, n, points?))
+}
+
+fn gens_checksum(header: &[u8], points: &[u8]) -> [u8; 32] {
+    let mut shake = Shake256::default();
+    shake.input(header);
+    shake.input(points);
+    let mut checksum = [0u8; 32];
+    shake.xof_result().read_exact(&mut checksum).unwrap();
+    checksum
+}
+
+fn check_rederived(derived: &[GroupElement], loaded: &[GroupElement]) -> Result<(), GensFileError> {
+    match derived.iter().zip(loaded).position(|(d, l)| d != l) {
+        Some(i) => Err(GensFileError::GeneratorMismatch(i)),
+        None => Ok(()),
+    }
+}
+
+impl MultiCommitGens {
+    fn points(&self) -> Vec<GroupElement> {
+        let mut points = self.G.clone();
+        points.push(self.h);
+        points
+    }
+
+    /// Encodes the generators together with the label they were derived from
+    pub fn to_bytes(&self, label: &[u8]) -> Vec<u8> {
+        encode_gens(GENS_KIND_MULTI_COMMIT, label, self.n, &self.points())
+    }
+
+    /// Decodes generators written by `to_bytes`, returning them with their
+    /// label. With `GensCheck::Rederive` they are also compared against
+    /// `MultiCommitGens::new(n, label)`, which costs as much as deriving them.
+    pub fn from_bytes(
+        bytes: &[u8],
+        check: GensCheck,
+    ) -> Result<(MultiCommitGens, Vec<u8>), GensFileError> {
+        let (label, n, points) = decode_gens(bytes, GENS_KIND_MULTI_COMMIT, 1)?;
+        if check == GensCheck::Rederive {
+            check_rederived(&MultiCommitGens::new(n, &label).points(), &points)?;
+        }
+
+        Ok((
+            MultiCommitGens {
+                n,
+                G: points[..n].to_vec(),
+                h: points[n],
+            },
+            label,
+        ))
+    }
+
+    pub fn save(&self, label: &[u8], path: impl AsRef<Path>) -> Result<(), GensFileError> {
+        fs::write(path, self.to_bytes(label))?;
+        Ok(())
+    }
+
+    pub fn load(
+        path: impl AsRef<Path>,
+        check: GensCheck,
+    ) -> Result<(MultiCommitGens, Vec<u8>), GensFileError> {
+        MultiCommitGens::from_bytes(&fs::read(path)?, check)
+    }
+}
+
 pub trait Commitments {
     fn commit(&self, blind: &Scalar, gens_n: &MultiCommitGens) -> GroupElement;
 }
@@ -87,11 +285,334 @@ pub struct DotProductProofGens {
     // n: usize,
     pub gens_n: MultiCommitGens,
     pub gens_1: MultiCommitGens,
+    // the label the generators were derived from
+    pub label: Vec<u8>,
+    // the encodings `fingerprint` hashes, of every G and then h and k, made on
+    // its first call; the points must not be changed once it is filled
+    fingerprint_points: OnceLock<Vec<CompressedGroup>>,
 }
 
 impl DotProductProofGens {
     pub fn new(n: usize, label: &[u8]) -> Self {
-        let (gens_n, gens_1) = MultiCommitGens::new(n + 1, label).split_at(n);
-        DotProductProofGens { gens_n, gens_1 }
+        let gens_n = MultiCommitGens::new(n, label);
+        let gens_1 = MultiCommitGens {
+            n: 1,
+            G: vec![derive_generator(label, b"k", 0)],
+            h: gens_n.h,
+        };
+        DotProductProofGens {
+            gens_n,
+            gens_1,
+            label: label.to_vec(),
+            fingerprint_points: OnceLock::new(),
+        }
+    }
+
+    /// The key for statements of length `n`, sliced from a larger one. It is
+    /// identical to `DotProductProofGens::new(n, label)`, so one key derived
+    /// (or loaded) for the largest statement serves all smaller ones.
+    pub fn prefix(&self, n: usize) -> DotProductProofGens {
+        DotProductProofGens {
+            gens_n: self.gens_n.prefix(n),
+            gens_1: self.gens_1.clone(),
+            label: self.label.clone(),
+            fingerprint_points: self.sliced_fingerprint_points(0..n),
+        }
+    }
+
+    /// The key made of `G_offset, G_{offset+1}, ...` with the same `h` and
+    /// `k`. A vector committed under it sits at `offset` in a vector stacked
+    /// under `self`, as the stages of `Pi_Staged_Chain_Proof` do.
+    pub fn suffix(&self, offset: usize) -> DotProductProofGens {
+        assert!(offset <= self.gens_n.n);
+        DotProductProofGens {
+            gens_n: MultiCommitGens {
+                n: self.gens_n.n - offset,
+                G: self.gens_n.G[offset..].to_vec(),
+                h: self.gens_n.h,
+            },
+            gens_1: self.gens_1.clone(),
+            label: self.label.clone(),
+            fingerprint_points: self.sliced_fingerprint_points(offset..self.gens_n.n),
+        }
+    }
+
+    // The encodings of G[range], h and k if this key has made them already
+    fn sliced_fingerprint_points(
+        &self,
+        range: std::ops::Range<usize>,
+    ) -> OnceLock<Vec<CompressedGroup>> {
+        let sliced = OnceLock::new();
+        if let Some(points) = self.fingerprint_points.get() {
+            let (G, hk) = points.split_at(self.gens_n.n);
+            let _ = sliced.set(G[range].iter().chain(hk).copied().collect());
+        }
+        sliced
+    }
+
+    /// A digest of the points `G[range]`, `h` and `k`, bound into statement
+    /// digests and transcripts so that a proof is tied to the actual points it
+    /// was made under, not just the label they are claimed to come from. Equal
+    /// points give equal fingerprints, so it does not matter whether the key
+    /// was derived, loaded or sliced from a larger one. The points are
+    /// encoded once per key, so later calls only hash.
+    pub fn fingerprint(&self, range: std::ops::Range<usize>) -> Result<[u8; 32], ProofVerifyError> {
+        if self.gens_n.n < range.end {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: range.end,
+                available: self.gens_n.n,
+            });
+        }
+        let mut shake = Shake256::default();
+        shake.input(b"Efficient_ZKP_for_Affine-forms generator fingerprint v1");
+        shake.input((range.start as u64).to_le_bytes());
+        shake.input((range.len() as u64).to_le_bytes());
+        // doubling is injective on the group, and the batch version saves an
+        // inversion per point over compressing them one by one
+        let points = self.fingerprint_points.get_or_init(|| {
+            GroupElement::double_and_compress_batch(
+                self.gens_n
+                    .G
+                    .iter()
+                    .chain([&self.gens_n.h, &self.gens_1.G[0]]),
+            )
+        });
+        let (G, hk) = points.split_at(self.gens_n.n);
+        for point in G[range].iter().chain(hk) {
+            shake.input(point.as_bytes());
+        }
+        let mut fingerprint = [0u8; 32];
+        shake.xof_result().read_exact(&mut fingerprint).unwrap();
+        Ok(fingerprint)
+    }
+
+    fn points(&self) -> Vec<GroupElement> {
+        let mut points = self.gens_n.points();
+        points.push(self.gens_1.G[0]);
+        points
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_gens(
+            GENS_KIND_DOT_PRODUCT,
+            &self.label,
+            self.gens_n.n,
+            &self.points(),
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8], check: GensCheck) -> Result<Self, GensFileError> {
+        let (label, n, points) = decode_gens(bytes, GENS_KIND_DOT_PRODUCT, 2)?;
+        if check == GensCheck::Rederive {
+            check_rederived(&DotProductProofGens::new(n, &label).points(), &points)?;
+        }
+
+        let h = points[n];
+        Ok(DotProductProofGens {
+            gens_n: MultiCommitGens {
+                n,
+                G: points[..n].to_vec(),
+                h,
+            },
+            gens_1: MultiCommitGens {
+                n: 1,
+                G: vec![points[n + 1]],
+                h,
+            },
+            label,
+            fingerprint_points: OnceLock::new(),
+        })
+    }
+
+    /// Writes the generators to `path`, so that provers and verifiers can
+    /// `load` them instead of deriving them on every run
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GensFileError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>, check: GensCheck) -> Result<Self, GensFileError> {
+        DotProductProofGens::from_bytes(&fs::read(path)?, check)
+    }
+}
+
+/// Generators for inner products of two committed vectors: `a` is committed
+/// under `gens_a` (the same `G` as `MultiCommitGens::new`), `b` under an
+/// independent vector `H` in `gens_b`, and the value under `k` in `gens_1`.
+/// All three share the blinding generator `h`.
+pub struct InnerProductProofGens {
+    pub gens_a: MultiCommitGens,
+    pub gens_b: MultiCommitGens,
+    pub gens_1: MultiCommitGens,
+    pub label: Vec<u8>,
+}
+
+impl InnerProductProofGens {
+    pub fn new(n: usize, label: &[u8]) -> Self {
+        let gens_a = MultiCommitGens::new(n, label);
+        let gens_b = MultiCommitGens::derive(n, label, b"H");
+        let gens_1 = MultiCommitGens {
+            n: 1,
+            G: vec![derive_generator(label, b"k", 0)],
+            h: gens_a.h,
+        };
+        InnerProductProofGens {
+            gens_a,
+            gens_b,
+            gens_1,
+            label: label.to_vec(),
+        }
+    }
+
+    pub fn prefix(&self, n: usize) -> InnerProductProofGens {
+        InnerProductProofGens {
+            gens_a: self.gens_a.prefix(n),
+            gens_b: self.gens_b.prefix(n),
+            gens_1: self.gens_1.clone(),
+            label: self.label.clone(),
+        }
+    }
+
+    /// The same key without `H`: identical to `DotProductProofGens::new` for
+    /// the label, so linear-form proofs can run over the same commitments
+    pub fn dot_product_gens(&self) -> DotProductProofGens {
+        DotProductProofGens {
+            gens_n: self.gens_a.clone(),
+            gens_1: self.gens_1.clone(),
+            label: self.label.clone(),
+            fingerprint_points: OnceLock::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_gens_are_prefix_stable() {
+        let large = DotProductProofGens::new(10, b"test-gens");
+        let small = DotProductProofGens::new(4, b"test-gens");
+        let sliced = large.prefix(4);
+
+        assert_eq!(sliced.gens_n.G, small.gens_n.G);
+        assert_eq!(sliced.gens_n.h, small.gens_n.h);
+        assert_eq!(sliced.gens_1.G, small.gens_1.G);
+        assert_eq!(sliced.points(), small.points());
+
+        // h and k are not among the G_i of any size
+        let gens = MultiCommitGens::new(11, b"test-gens");
+        assert!(!gens.G.contains(&large.gens_n.h));
+        assert!(!gens.G.contains(&large.gens_1.G[0]));
+        assert_ne!(MultiCommitGens::new(4, b"other-gens").G, small.gens_n.G);
+    }
+
+    #[test]
+    fn check_gens_file_round_trip() {
+        let gens = DotProductProofGens::new(7, b"test-gens");
+        let bytes = gens.to_bytes();
+        // header, checksum and n + 2 points
+        assert_eq!(bytes.len(), 4 + 1 + 1 + 4 + 9 + 4 + 32 + 9 * 32);
+
+        for check in [GensCheck::Checksum, GensCheck::Rederive] {
+            let loaded = DotProductProofGens::from_bytes(&bytes, check).unwrap();
+            assert_eq!(loaded.label, gens.label);
+            assert_eq!(loaded.gens_n.n, 7);
+            assert_eq!(loaded.gens_n.G, gens.gens_n.G);
+            assert_eq!(loaded.gens_n.h, gens.gens_n.h);
+            assert_eq!(loaded.gens_1.G, gens.gens_1.G);
+            assert_eq!(loaded.gens_1.h, gens.gens_1.h);
+        }
+
+        let multi = MultiCommitGens::new(5, b"test-gens");
+        let (loaded, label) =
+            MultiCommitGens::from_bytes(&multi.to_bytes(b"test-gens"), GensCheck::Rederive)
+                .unwrap();
+        assert_eq!(label, b"test-gens");
+        assert_eq!(loaded.G, multi.G);
+        assert_eq!(loaded.h, multi.h);
+
+        // the kind byte keeps the two key types apart
+        assert!(matches!(
+            MultiCommitGens::from_bytes(&bytes, GensCheck::Checksum),
+            Err(GensFileError::UnexpectedKind {
+                expected: 0,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn check_gens_file_rejects_bad_input() {
+        let gens = DotProductProofGens::new(3, b"test-gens");
+        let bytes = gens.to_bytes();
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            DotProductProofGens::from_bytes(&corrupted, GensCheck::Checksum),
+            Err(GensFileError::ChecksumMismatch)
+        ));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            DotProductProofGens::from_bytes(&bad_magic, GensCheck::Checksum),
+            Err(GensFileError::BadMagic)
+        ));
+
+        assert!(matches!(
+            DotProductProofGens::from_bytes(&bytes[..bytes.len() - 1], GensCheck::Checksum),
+            Err(GensFileError::Malformed(ProofDecodeError::UnexpectedEnd))
+        ));
+
+        // a consistent file whose points were not derived from its label
+        // passes the checksum but not the re-derivation
+        let relabeled = encode_gens(GENS_KIND_DOT_PRODUCT, b"other-gens", 3, &gens.points());
+        assert!(DotProductProofGens::from_bytes(&relabeled, GensCheck::Checksum).is_ok());
+        assert!(matches!(
+            DotProductProofGens::from_bytes(&relabeled, GensCheck::Rederive),
+            Err(GensFileError::GeneratorMismatch(0))
+        ));
+    }
+
+    #[test]
+    fn check_gens_fingerprint() {
+        let gens = DotProductProofGens::new(4, b"test-gens");
+        let fingerprint = gens.fingerprint(0..4).unwrap();
+
+        // the same points give the same fingerprint however the key was made
+        let large = DotProductProofGens::new(10, b"test-gens");
+        assert_eq!(large.fingerprint(0..4).unwrap(), fingerprint);
+        assert_eq!(large.prefix(4).fingerprint(0..4).unwrap(), fingerprint);
+        // prefix and suffix carry the encodings large has made by now
+        assert_eq!(large.prefix(6).fingerprint(0..4).unwrap(), fingerprint);
+        let fresh = DotProductProofGens::new(10, b"test-gens");
+        assert_eq!(
+            large.suffix(3).fingerprint(1..5).unwrap(),
+            fresh.suffix(3).fingerprint(1..5).unwrap()
+        );
+        let loaded =
+            DotProductProofGens::from_bytes(&gens.to_bytes(), GensCheck::Checksum).unwrap();
+        assert_eq!(loaded.fingerprint(0..4).unwrap(), fingerprint);
+
+        // a file that claims the label but carries other points does not
+        let other = DotProductProofGens::new(4, b"other-gens");
+        let substituted = encode_gens(GENS_KIND_DOT_PRODUCT, b"test-gens", 4, &other.points());
+        let substituted =
+            DotProductProofGens::from_bytes(&substituted, GensCheck::Checksum).unwrap();
+        assert_eq!(substituted.label, gens.label);
+        assert_ne!(substituted.fingerprint(0..4).unwrap(), fingerprint);
+
+        // nor does another range of the same key
+        assert_ne!(large.fingerprint(0..5).unwrap(), fingerprint);
+        assert_ne!(large.fingerprint(4..8).unwrap(), fingerprint);
+        assert!(matches!(
+            gens.fingerprint(2..5),
+            Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: 5,
+                available: 4
+            })
+        ));
     }
 }
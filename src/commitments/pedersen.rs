@@ -0,0 +1,252 @@
+//! Typed Pedersen vector commitments.
+//!
+//! A `Commitment` is `C = <x, G> + gamma * h` under a `MultiCommitGens`, and
+//! its `Opening` is the pair `(x, gamma)`. Commitments and openings add and
+//! scale together, so an aggregate commitment built from commitments one
+//! already holds is opened by the same combination of their openings:
+//!
+//! `(&c_1 + &c_2) * &s` is opened by `(&o_1 + &o_2) * &s`.
+//!
+//! Since generators are derived per index, a vector of length `m` is
+//! committed under the first `m` generators of any key, and openings of
+//! different lengths are combined by padding the shorter one with zeros.
+use crate::commitments::{Commitments, MultiCommitGens};
+use crate::curve25519::errors::ProofVerifyError;
+use crate::curve25519::group::{CompressedGroup, CompressedGroupExt, GroupElement};
+use crate::curve25519::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul, Neg, Range, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commitment(GroupElement);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opening {
+    pub x_vec: Vec<Scalar>,
+    pub gamma: Scalar,
+}
+
+impl Commitment {
+    pub fn from_compressed(P: &CompressedGroup) -> Result<Self, ProofVerifyError> {
+        Ok(Commitment(P.unpack()?))
+    }
+
+    /// The form `Pi_Affine_Proof` and `Pi_c_Proof` take as `P`
+    pub fn compress(&self) -> CompressedGroup {
+        self.0.compress()
+    }
+
+    pub fn point(&self) -> &GroupElement {
+        &self.0
+    }
+
+    pub fn verify_opening(&self, gens: &MultiCommitGens, opening: &Opening) -> bool {
+        gens.n >= opening.x_vec.len() && opening.commit(gens) == *self
+    }
+
+    /// Adds `delta * h`; the opening is updated by `Opening::rerandomize`
+    /// with the same `delta`
+    pub fn rerandomize(&self, gens: &MultiCommitGens, delta: &Scalar) -> Commitment {
+        Commitment(self.0 + delta * gens.h)
+    }
+}
+
+impl Opening {
+    pub fn new(x_vec: Vec<Scalar>, gamma: Scalar) -> Self {
+        Opening { x_vec, gamma }
+    }
+
+    pub fn random<R: RngCore + CryptoRng>(x_vec: Vec<Scalar>, rng: &mut R) -> Self {
+        Opening {
+            x_vec,
+            gamma: Scalar::random(rng),
+        }
+    }
+
+    pub fn commit(&self, gens: &MultiCommitGens) -> Commitment {
+        assert!(gens.n >= self.x_vec.len());
+        Commitment(
+            self.x_vec
+                .commit(&self.gamma, &gens.prefix(self.x_vec.len())),
+        )
+    }
+
+    /// The opening of the entries of `x` in `range` alone, with a fresh
+    /// blinding `gamma`: the vector keeps its positions (and is zero before
+    /// `range.start`), so it is committed under the generators of `range`.
+    /// Commitments to the sub-ranges of a partition of `x` add up to a
+    /// commitment to `x` whose blinding is the sum of theirs.
+    pub fn sub_range(&self, range: Range<usize>, gamma: Scalar) -> Opening {
+        assert!(range.start <= range.end && range.end <= self.x_vec.len());
+        let mut x_vec = vec![Scalar::zero(); range.end];
+        x_vec[range.clone()].copy_from_slice(&self.x_vec[range]);
+        Opening { x_vec, gamma }
+    }
+
+    pub fn rerandomize(&self, delta: &Scalar) -> Opening {
+        Opening {
+            x_vec: self.x_vec.clone(),
+            gamma: self.gamma + delta,
+        }
+    }
+}
+
+impl<'b> Add<&'b Commitment> for &Commitment {
+    type Output = Commitment;
+
+    fn add(self, other: &'b Commitment) -> Commitment {
+        Commitment(self.0 + other.0)
+    }
+}
+
+impl<'b> Sub<&'b Commitment> for &Commitment {
+    type Output = Commitment;
+
+    fn sub(self, other: &'b Commitment) -> Commitment {
+        Commitment(self.0 - other.0)
+    }
+}
+
+impl<'b> Mul<&'b Scalar> for &Commitment {
+    type Output = Commitment;
+
+    fn mul(self, scalar: &'b Scalar) -> Commitment {
+        Commitment(self.0 * scalar)
+    }
+}
+
+impl<'b> Add<&'b Opening> for &Opening {
+    type Output = Opening;
+
+    fn add(self, other: &'b Opening) -> Opening {
+        let (long, short) = if self.x_vec.len() >= other.x_vec.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut x_vec = long.x_vec.clone();
+        for (x, y) in x_vec.iter_mut().zip(short.x_vec.iter()) {
+            *x += y;
+        }
+        Opening {
+            x_vec,
+            gamma: self.gamma + other.gamma,
+        }
+    }
+}
+
+impl Neg for &Opening {
+    type Output = Opening;
+
+    fn neg(self) -> Opening {
+        Opening {
+            x_vec: self.x_vec.iter().map(|x| -x).collect(),
+            gamma: -self.gamma,
+        }
+    }
+}
+
+impl<'b> Sub<&'b Opening> for &Opening {
+    type Output = Opening;
+
+    fn sub(self, other: &'b Opening) -> Opening {
+        self + &(-other)
+    }
+}
+
+impl<'b> Mul<&'b Scalar> for &Opening {
+    type Output = Opening;
+
+    fn mul(self, scalar: &'b Scalar) -> Opening {
+        Opening {
+            x_vec: self.x_vec.iter().map(|x| x * scalar).collect(),
+            gamma: self.gamma * scalar,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitments::DotProductProofGens;
+    use crate::curve25519::scalar_math;
+    use crate::random::RandomTape;
+    use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+    use crate::zk_protocol::pi_c_protocol::ProveOptions;
+    use merlin::Transcript;
+    use rand::rngs::OsRng;
+
+    fn random_opening(m: usize) -> Opening {
+        let mut csprng: OsRng = OsRng;
+        let x_vec = (0..m).map(|_| Scalar::random(&mut csprng)).collect();
+        Opening::random(x_vec, &mut csprng)
+    }
+
+    #[test]
+    fn check_commitment_arithmetic() {
+        let mut csprng: OsRng = OsRng;
+        let gens = MultiCommitGens::new(8, b"test-gens");
+        let (o_1, o_2) = (random_opening(8), random_opening(5));
+        let (c_1, c_2) = (o_1.commit(&gens), o_2.commit(&gens));
+        assert!(c_1.verify_opening(&gens, &o_1));
+        assert!(!c_1.verify_opening(&gens, &o_2));
+
+        let s = Scalar::random(&mut csprng);
+        let aggregate = &(&(&c_1 + &c_2) * &s) - &c_2;
+        let opening = &(&(&o_1 + &o_2) * &s) - &o_2;
+        assert!(aggregate.verify_opening(&gens, &opening));
+
+        let delta = Scalar::random(&mut csprng);
+        let rerandomized = c_1.rerandomize(&gens, &delta);
+        assert_ne!(rerandomized, c_1);
+        assert!(rerandomized.verify_opening(&gens, &o_1.rerandomize(&delta)));
+
+        // sub-ranges of a partition add up to the whole vector
+        let (g_1, g_2) = (Scalar::random(&mut csprng), Scalar::random(&mut csprng));
+        let (head, tail) = (o_1.sub_range(0..3, g_1), o_1.sub_range(3..8, g_2));
+        let sum = &head.commit(&gens) + &tail.commit(&gens);
+        assert!(sum.verify_opening(&gens, &Opening::new(o_1.x_vec.clone(), g_1 + g_2)));
+
+        let compressed = Commitment::from_compressed(&c_1.compress()).unwrap();
+        assert_eq!(compressed, c_1);
+    }
+
+    #[test]
+    fn check_aggregate_commitment_in_affine_proof() {
+        let mut csprng: OsRng = OsRng;
+        let n = 6;
+        let gens = DotProductProofGens::new(n, b"test-gens");
+        let (o_1, o_2) = (random_opening(n), random_opening(n));
+        let s = Scalar::random(&mut csprng);
+        let commitment = &o_1.commit(&gens.gens_n) + &(&o_2.commit(&gens.gens_n) * &s);
+        let opening = &o_1 + &(&o_2 * &s);
+
+        let m_matric: Vec<Vec<Scalar>> = (0..3)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect();
+        let b_vec: Vec<Scalar> = m_matric
+            .iter()
+            .map(|row| scalar_math::compute_linearform(row, &opening.x_vec))
+            .collect();
+
+        let P = commitment.compress();
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, _) = Pi_Affine_Proof::prove_affine(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &opening.x_vec,
+            &opening.gamma,
+            &m_matric,
+            &b_vec,
+            &ProveOptions::default().with_commitment(&P),
+        );
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_affine(n, &gens, &mut verifier_transcript, &m_matric, &b_vec, &P)
+            .is_ok());
+    }
+}
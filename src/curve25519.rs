@@ -1,4 +1,6 @@
+pub mod canonical;
 pub mod errors;
 pub mod group;
 pub mod scalar;
 pub mod scalar_math;
+pub mod sparse_matrix;
@@ -0,0 +1,405 @@
+//! Human-readable serde formats for `Scalar` and `CompressedGroup`.
+//!
+//! Deserializing a `Scalar` from a human-readable format accepts any of:
+//!   - `"0x"` followed by 64 hex digits, the canonical little-endian bytes,
+//!   - a decimal string, optionally negative, reduced mod q,
+//!   - a plain (possibly negative) integer that fits in 64 bits,
+//!   - the legacy four Montgomery limbs `[u64; 4]`.
+//!
+//! so statement and witness files can be produced outside this crate. The
+//! default `Serialize` of `Scalar` still writes limbs; fields opt into a
+//! canonical encoding with `#[serde(with = "canonical::hex")]` or
+//! `#[serde(with = "canonical::decimal")]`, which work on `Scalar`,
+//! `CompressedGroup` and (nested) vectors of them. Points are always written
+//! as hex, and read from hex or from the legacy 32-byte array.
+use super::group::CompressedGroup;
+use super::scalar::Scalar;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Hex,
+    Decimal,
+}
+
+pub trait Canonical: Sized {
+    fn serialize_canonical<S: Serializer>(
+        &self,
+        serializer: S,
+        format: Format,
+    ) -> Result<S::Ok, S::Error>;
+
+    fn deserialize_canonical<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+pub mod hex {
+    use super::{Canonical, Format};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T: Canonical, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.serialize_canonical(serializer, Format::Hex)
+    }
+
+    pub fn deserialize<'de, T: Canonical, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_canonical(deserializer)
+    }
+}
+
+pub mod decimal {
+    use super::{Canonical, Format};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T: Canonical, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.serialize_canonical(serializer, Format::Decimal)
+    }
+
+    pub fn deserialize<'de, T: Canonical, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_canonical(deserializer)
+    }
+}
+
+fn encode_hex(bytes: &[u8; 32]) -> String {
+    let mut s = String::with_capacity(66);
+    s.push_str("0x");
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn decode_hex(s: &str) -> Option<[u8; 32]> {
+    let digits = s.strip_prefix("0x")?.as_bytes();
+    // from_str_radix alone would also take a sign, e.g. "+f"
+    if digits.len() != 64 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        let pair = std::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+// Decimal digits of the canonical representative in [0, q)
+fn encode_decimal(scalar: &Scalar) -> String {
+    let mut value = scalar.to_bytes();
+    let mut digits: Vec<u8> = Vec::new();
+    while value.iter().any(|b| *b != 0) {
+        // value = value / 10, from the most significant byte down
+        let mut rem: u16 = 0;
+        for b in value.iter_mut().rev() {
+            let cur = (rem << 8) | *b as u16;
+            *b = (cur / 10) as u8;
+            rem = cur % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+// Accepts any number of digits, reducing mod q as it goes
+fn decode_decimal(s: &str) -> Option<Scalar> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    if digits.is_empty() || !digits.bytes().all(|d| d.is_ascii_digit()) {
+        return None;
+    }
+    let ten = Scalar::from(10u64);
+    let value = digits.bytes().fold(Scalar::zero(), |acc, d| {
+        acc * ten + Scalar::from((d - b'0') as u64)
+    });
+    Some(if negative { -value } else { value })
+}
+
+fn scalar_from_u128(value: u128) -> Scalar {
+    Scalar::from((value >> 64) as u64) * Scalar::from(1u64 << 32) * Scalar::from(1u64 << 32)
+        + Scalar::from(value as u64)
+}
+
+fn scalar_from_i128(value: i128) -> Scalar {
+    if value < 0 {
+        -scalar_from_u128(value.unsigned_abs())
+    } else {
+        scalar_from_u128(value as u128)
+    }
+}
+
+pub(crate) struct ScalarVisitor;
+
+impl<'de> Visitor<'de> for ScalarVisitor {
+    type Value = Scalar;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            "an integer, a decimal string, 0x-prefixed little-endian hex or [u64; 4] limbs",
+        )
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Scalar, E> {
+        Ok(Scalar::from(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Scalar, E> {
+        Ok(scalar_from_i128(value as i128))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Scalar, E> {
+        Ok(scalar_from_u128(value))
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Scalar, E> {
+        Ok(scalar_from_i128(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Scalar, E> {
+        if value.starts_with("0x") {
+            let bytes = decode_hex(value)
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))?;
+            Option::from(Scalar::from_bytes(&bytes))
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &"a canonical scalar"))
+        } else {
+            decode_decimal(value).ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Scalar, A::Error> {
+        let limbs = <[u64; 4]>::deserialize(de::value::SeqAccessDeserializer::new(seq))?;
+        Option::from(Scalar::from_raw_limbs(limbs))
+            .ok_or_else(|| de::Error::custom("scalar limbs are not below the modulus"))
+    }
+}
+
+impl Canonical for Scalar {
+    fn serialize_canonical<S: Serializer>(
+        &self,
+        serializer: S,
+        format: Format,
+    ) -> Result<S::Ok, S::Error> {
+        match format {
+            Format::Hex => serializer.serialize_str(&encode_hex(&self.to_bytes())),
+            Format::Decimal => serializer.serialize_str(&encode_decimal(self)),
+        }
+    }
+
+    fn deserialize_canonical<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Scalar::deserialize(deserializer)
+    }
+}
+
+struct PointVisitor;
+
+impl<'de> Visitor<'de> for PointVisitor {
+    type Value = CompressedGroup;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("0x-prefixed hex or an array of 32 bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<CompressedGroup, E> {
+        decode_hex(value)
+            .map(CompressedRistretto)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<CompressedGroup, A::Error> {
+        CompressedGroup::deserialize(de::value::SeqAccessDeserializer::new(seq))
+    }
+}
+
+impl Canonical for CompressedGroup {
+    fn serialize_canonical<S: Serializer>(
+        &self,
+        serializer: S,
+        _format: Format,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode_hex(self.as_bytes()))
+    }
+
+    fn deserialize_canonical<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let point = if deserializer.is_human_readable() {
+            deserializer.deserialize_any(PointVisitor)?
+        } else {
+            CompressedGroup::deserialize(deserializer)?
+        };
+        // reject encodings that do not decompress here rather than at verify time
+        match point.decompress() {
+            Some(_) => Ok(point),
+            None => Err(de::Error::custom("point does not decompress")),
+        }
+    }
+}
+
+struct Ser<'a, T>(&'a T, Format);
+
+impl<'a, T: Canonical> Serialize for Ser<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_canonical(serializer, self.1)
+    }
+}
+
+struct De<T>(T);
+
+impl<'de, T: Canonical> Deserialize<'de> for De<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_canonical(deserializer).map(De)
+    }
+}
+
+impl<T: Canonical> Canonical for Vec<T> {
+    fn serialize_canonical<S: Serializer>(
+        &self,
+        serializer: S,
+        format: Format,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(|value| Ser(value, format)))
+    }
+
+    fn deserialize_canonical<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VecVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Canonical> Visitor<'de> for VecVisitor<T> {
+            type Value = Vec<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+                let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(De(value)) = seq.next_element::<De<T>>()? {
+                    values.push(value);
+                }
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_seq(VecVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve25519::group::GROUP_BASEPOINT_COMPRESSED;
+    use rand::rngs::OsRng;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        #[serde(with = "hex")]
+        x: Scalar,
+        #[serde(with = "decimal")]
+        row: Vec<Scalar>,
+        #[serde(with = "hex")]
+        P: CompressedGroup,
+    }
+
+    #[test]
+    fn check_canonical_round_trip() {
+        let mut csprng: OsRng = OsRng;
+        let sample = Sample {
+            x: Scalar::random(&mut csprng),
+            row: vec![
+                Scalar::zero(),
+                Scalar::one(),
+                -Scalar::one(),
+                Scalar::random(&mut csprng),
+            ],
+            P: GROUP_BASEPOINT_COMPRESSED,
+        };
+
+        let json = serde_json::to_string(&sample).unwrap();
+        assert_eq!(serde_json::from_str::<Sample>(&json).unwrap(), sample);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["row"][1], "1");
+        assert_eq!(
+            value["row"][2],
+            "7237005577332262213973186563042994240857116359379907606001950938285454250988"
+        );
+    }
+
+    #[test]
+    fn check_scalar_input_formats() {
+        let parse = |s: &str| serde_json::from_str::<Scalar>(s).unwrap();
+
+        assert_eq!(parse("5"), Scalar::from(5u64));
+        assert_eq!(parse("-5"), -Scalar::from(5u64));
+        assert_eq!(parse("\"-5\""), -Scalar::from(5u64));
+        assert_eq!(
+            parse("\"18446744073709551616\""),
+            Scalar::from(1u64 << 32) * Scalar::from(1u64 << 32)
+        );
+        // q itself and q + 1 reduce to 0 and 1
+        assert_eq!(
+            parse(
+                "\"7237005577332262213973186563042994240857116359379907606001950938285454250989\""
+            ),
+            Scalar::zero()
+        );
+        assert_eq!(
+            parse(
+                "\"7237005577332262213973186563042994240857116359379907606001950938285454250990\""
+            ),
+            Scalar::one()
+        );
+        let mut hex = String::from("\"0x05");
+        hex.push_str(&"0".repeat(62));
+        hex.push('"');
+        assert_eq!(parse(&hex), Scalar::from(5u64));
+
+        // the limbs written by the default `Serialize` are still accepted
+        let limbs = serde_json::to_string(&Scalar::from(5u64)).unwrap();
+        assert!(limbs.starts_with('['));
+        assert_eq!(parse(&limbs), Scalar::from(5u64));
+
+        assert!(serde_json::from_str::<Scalar>("\"12a\"").is_err());
+        assert!(serde_json::from_str::<Scalar>("\"0x05\"").is_err());
+        let signed = hex.replacen("0x05", "0x+5", 1);
+        assert!(serde_json::from_str::<Scalar>(&signed).is_err());
+        // hex must be canonical: q encodes as ed d3 f5 5c ... 10
+        let q = "\"0xedd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010\"";
+        assert!(serde_json::from_str::<Scalar>(q).is_err());
+        // so must limbs: the Montgomery form is reduced mod q
+        let max = format!("[{0},{0},{0},{0}]", u64::MAX);
+        assert!(serde_json::from_str::<Scalar>(&max).is_err());
+    }
+
+    #[test]
+    fn check_point_must_decompress() {
+        let valid = serde_json::to_string(&Sample {
+            x: Scalar::one(),
+            row: vec![],
+            P: GROUP_BASEPOINT_COMPRESSED,
+        })
+        .unwrap();
+        assert!(serde_json::from_str::<Sample>(&valid).is_ok());
+
+        let invalid = valid.replace(
+            &encode_hex(GROUP_BASEPOINT_COMPRESSED.as_bytes()),
+            &encode_hex(&[0xff; 32]),
+        );
+        assert!(serde_json::from_str::<Sample>(&invalid).is_err());
+    }
+}
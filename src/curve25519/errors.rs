@@ -1,16 +1,71 @@
 use core::fmt::Debug;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Default)]
 pub enum ProofVerifyError {
     #[error("Proof verification failed")]
+    #[default]
     InternalError,
     #[error("Compressed group element failed to decompress: {0:?}")]
     DecompressionError([u8; 32]),
+    #[error("Batch verification failed for proofs {0:?}")]
+    BatchVerificationError(Vec<usize>),
+    #[error("Expected a vector of length {expected}, found {found}")]
+    LengthMismatch { expected: usize, found: usize },
+    #[error("Generators support vectors of length {available}, but {needed} are needed")]
+    GeneratorsTooSmall { needed: usize, available: usize },
+    #[error("Expected {expected} folding rounds, found {found}")]
+    RoundCountMismatch { expected: usize, found: usize },
+    #[error("Fiat-Shamir challenge is zero")]
+    ZeroChallenge,
+    #[error("Statement is malformed: {0}")]
+    MalformedStatement(String),
+    #[error("Final verification equation failed")]
+    FinalCheckFailed,
+    #[error("Proof's output value does not match the public offset b")]
+    OffsetMismatch,
 }
 
-impl Default for ProofVerifyError {
-    fn default() -> Self {
-        ProofVerifyError::InternalError
-    }
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProofDecodeError {
+    #[error("Proof encoding does not start with the expected magic bytes")]
+    BadMagic,
+    #[error("Unsupported proof encoding version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Expected proof type {expected}, found {found}")]
+    UnexpectedProofType { expected: u8, found: u8 },
+    #[error("Proof encoding ended unexpectedly")]
+    UnexpectedEnd,
+    #[error("Proof encoding has {0} trailing bytes")]
+    TrailingBytes(usize),
+    #[error("Scalar is not canonically encoded: {0:?}")]
+    NonCanonicalScalar([u8; 32]),
+    #[error("Compressed group element failed to decompress: {0:?}")]
+    InvalidPoint([u8; 32]),
+    #[error("Vector of length {len} exceeds the maximum of {max}")]
+    VectorTooLong { len: usize, max: usize },
+    #[error("Expected a vector of length {expected}, found {found}")]
+    UnexpectedLength { expected: usize, found: usize },
+    #[error("Unknown Pi_c response mode {0}")]
+    UnknownMode(u8),
+}
+
+#[derive(Error, Debug)]
+pub enum GensFileError {
+    #[error("Failed to read or write the generators file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Generators file does not start with the expected magic bytes")]
+    BadMagic,
+    #[error("Unsupported generators file version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Expected a generators file of kind {expected}, found {found}")]
+    UnexpectedKind { expected: u8, found: u8 },
+    #[error("Malformed generators file: {0}")]
+    Malformed(#[from] ProofDecodeError),
+    #[error("Generators file checksum does not match its contents")]
+    ChecksumMismatch,
+    #[error("Generator {0} is not a valid point")]
+    InvalidPoint(usize),
+    #[error("Generator {0} differs from the one derived from the label")]
+    GeneratorMismatch(usize),
 }
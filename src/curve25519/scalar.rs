@@ -13,7 +13,8 @@ use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 use num_traits::{One, Zero};
 use rand::{CryptoRng, RngCore};
-use serde::{Deserialize, Serialize};
+use super::canonical::ScalarVisitor;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 use zeroize::Zeroize;
 
@@ -269,9 +270,23 @@ macro_rules! impl_binops_multiplicative {
 // The internal representation of this type is four 64-bit unsigned
 // integers in little-endian order. `Scalar` values are always in
 // Montgomery form; i.e., Scalar(a) = aR mod q, with R = 2^256.
-#[derive(Clone, Copy, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Eq, Serialize)]
 pub struct Scalar(pub(crate) [u64; 4]);
 
+// Human-readable formats accept the canonical encodings of `canonical` as
+// well as the limbs written by `Serialize`
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(ScalarVisitor)
+        } else {
+            let limbs = <[u64; 4]>::deserialize(deserializer)?;
+            Option::from(Scalar::from_raw_limbs(limbs))
+                .ok_or_else(|| de::Error::custom("scalar limbs are not below the modulus"))
+        }
+    }
+}
+
 impl fmt::Debug for Scalar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let tmp = self.to_bytes();
@@ -439,6 +454,17 @@ impl Zeroize for Scalar {
 }
 
 impl Scalar {
+    // The internal Montgomery limbs, as written by `Serialize`, failing unless
+    // they are below the modulus
+    pub(crate) fn from_raw_limbs(limbs: [u64; 4]) -> CtOption<Scalar> {
+        let (_, borrow) = sbb(limbs[0], MODULUS.0[0], 0);
+        let (_, borrow) = sbb(limbs[1], MODULUS.0[1], borrow);
+        let (_, borrow) = sbb(limbs[2], MODULUS.0[2], borrow);
+        let (_, borrow) = sbb(limbs[3], MODULUS.0[3], borrow);
+
+        CtOption::new(Scalar(limbs), Choice::from((borrow as u8) & 1))
+    }
+
     /// Returns zero, the additive identity.
     #[inline]
     pub const fn zero() -> Scalar {
@@ -855,6 +881,8 @@ impl<'a> From<&'a Scalar> for [u8; 32] {
 }
 
 #[cfg(test)]
+// `test_debug` is gated on a `std` feature this crate does not declare
+#[allow(unexpected_cfgs)]
 mod tests {
     use super::*;
 
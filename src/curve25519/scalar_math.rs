@@ -1,9 +1,18 @@
 use super::scalar::Scalar;
+#[cfg(feature = "multicore")]
+use rayon::prelude::*;
 
 // compute linear form $y=L(\vec{x})$
 pub fn compute_linearform(a: &[Scalar], b: &[Scalar]) -> Scalar {
     assert_eq!(a.len(), b.len());
-    (0..a.len()).map(|i| a[i] * b[i]).sum()
+
+    #[cfg(feature = "multicore")]
+    let y = a.par_iter().zip(b.par_iter()).map(|(a, b)| a * b).sum();
+
+    #[cfg(not(feature = "multicore"))]
+    let y = (0..a.len()).map(|i| a[i] * b[i]).sum();
+
+    y
 }
 
 /// Computes an inner product of two vectors
@@ -44,15 +53,59 @@ pub fn vandemonde_challenge_one(x: Scalar, s: usize) -> Vec<Scalar> {
     challenges
 }
 
-// Takes a matrix and a vector
-// Returns a new vector i.e. (Ax=b)
-pub fn matrix_vector_mul(matrix: &Vec<Vec<Scalar>>, vec: &[Scalar]) -> Vec<Scalar> {
-    matrix
+// Evaluates the Lagrange basis over the nodes 0, 1, ..., d at `x`: the
+// returned l_i satisfy p(x) = sum_i l_i * p(i) for every p of degree <= d
+pub fn lagrange_coefficients(x: Scalar, d: usize) -> Vec<Scalar> {
+    let diffs: Vec<Scalar> = (0..=d as u64).map(|j| x - Scalar::from(j)).collect();
+
+    // prod_{j != i} (x - j) from prefix and suffix products
+    let mut suffix = vec![Scalar::one(); d + 2];
+    for j in (0..=d).rev() {
+        suffix[j] = suffix[j + 1] * diffs[j];
+    }
+    let mut numerators = Vec::with_capacity(d + 1);
+    let mut prefix = Scalar::one();
+    for j in 0..=d {
+        numerators.push(prefix * suffix[j + 1]);
+        prefix *= diffs[j];
+    }
+
+    // prod_{j != i} (i - j) = i! (d - i)! (-1)^(d - i)
+    let mut factorials = vec![Scalar::one(); d + 1];
+    for j in 1..=d {
+        factorials[j] = factorials[j - 1] * Scalar::from(j as u64);
+    }
+    let mut denominators: Vec<Scalar> = (0..=d)
+        .map(|i| {
+            let den = factorials[i] * factorials[d - i];
+            if (d - i) % 2 == 1 {
+                -den
+            } else {
+                den
+            }
+        })
+        .collect();
+    Scalar::batch_invert(&mut denominators);
+
+    numerators
         .iter()
-        .map(|row| compute_linearform(row, &vec))
+        .zip(denominators.iter())
+        .map(|(n, d)| n * d)
         .collect()
 }
 
+// Takes a matrix and a vector
+// Returns a new vector i.e. (Ax=b)
+pub fn matrix_vector_mul(matrix: &[Vec<Scalar>], vec: &[Scalar]) -> Vec<Scalar> {
+    #[cfg(feature = "multicore")]
+    let iter = matrix.par_iter();
+
+    #[cfg(not(feature = "multicore"))]
+    let iter = matrix.iter();
+
+    iter.map(|row| compute_linearform(row, vec)).collect()
+}
+
 // Takes two rows and adds them together
 // The (i) entry of row a is added to the i entry of row b
 pub fn row_row_add(row_a: &[Scalar], row_b: &[Scalar]) -> Vec<Scalar> {
@@ -62,12 +115,22 @@ pub fn row_row_add(row_a: &[Scalar], row_b: &[Scalar]) -> Vec<Scalar> {
 }
 
 // Takes the transpose of a matrix
-pub fn matrix_transpose(matrix: &Vec<Vec<Scalar>>) -> Vec<Vec<Scalar>> {
+#[cfg(feature = "multicore")]
+pub fn matrix_transpose(matrix: &[Vec<Scalar>]) -> Vec<Vec<Scalar>> {
+    (0..matrix[0].len())
+        .into_par_iter()
+        .map(|i| matrix.iter().map(|row| row[i]).collect())
+        .collect()
+}
+
+// Takes the transpose of a matrix
+#[cfg(not(feature = "multicore"))]
+pub fn matrix_transpose(matrix: &[Vec<Scalar>]) -> Vec<Vec<Scalar>> {
     let mut transpose: Vec<Vec<Scalar>> = vec![Vec::new(); matrix[0].len()];
 
-    for (_, row) in matrix.iter().enumerate() {
+    for row in matrix.iter() {
         for (i, element) in row.iter().enumerate() {
-            transpose[i].push(element.clone());
+            transpose[i].push(*element);
         }
     }
 
@@ -81,18 +144,18 @@ pub fn scalar_vector_mul(a: &Scalar, vec: &[Scalar]) -> Vec<Scalar> {
 }
 
 pub fn zeros(size: usize) -> Vec<Scalar> {
-    let mut zero_vec: Vec<Scalar> = Vec::with_capacity(size as usize);
+    let mut zero_vec: Vec<Scalar> = Vec::with_capacity(size);
     for _ in 0..size {
         zero_vec.push(Scalar::zero());
     }
-    return zero_vec;
+    zero_vec
 }
 
 pub fn negOnes(size: usize) -> Vec<Scalar> {
-    let mut zero_vec: Vec<Scalar> = Vec::with_capacity(size as usize);
+    let mut zero_vec: Vec<Scalar> = Vec::with_capacity(size);
     let negOne = -Scalar::one();
     for _ in 0..size {
         zero_vec.push(negOne);
     }
-    return zero_vec;
+    zero_vec
 }
@@ -0,0 +1,85 @@
+use super::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+
+// A matrix in coordinate (COO) format: only the nonzero entries
+// (row, col, value) are stored, in any order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparseMatrix {
+    pub num_rows: usize,
+    pub num_cols: usize,
+    pub entries: Vec<(usize, usize, Scalar)>,
+}
+
+impl SparseMatrix {
+    pub fn new(num_rows: usize, num_cols: usize, entries: Vec<(usize, usize, Scalar)>) -> Self {
+        let matrix = SparseMatrix {
+            num_rows,
+            num_cols,
+            entries,
+        };
+        assert!(matrix.is_well_formed());
+        matrix
+    }
+
+    pub fn from_dense(matrix: &[Vec<Scalar>]) -> Self {
+        assert!(!matrix.is_empty());
+        let num_cols = matrix[0].len();
+        let mut entries: Vec<(usize, usize, Scalar)> = Vec::new();
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row.len(), num_cols);
+            for (j, element) in row.iter().enumerate() {
+                if *element != Scalar::zero() {
+                    entries.push((i, j, *element));
+                }
+            }
+        }
+        SparseMatrix {
+            num_rows: matrix.len(),
+            num_cols,
+            entries,
+        }
+    }
+
+    // All entries lie inside the declared dimensions
+    pub fn is_well_formed(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|(i, j, _)| *i < self.num_rows && *j < self.num_cols)
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Appends `col` as a new last column, keeping only its nonzero values
+    pub fn append_column(&mut self, col: &[Scalar]) {
+        assert_eq!(col.len(), self.num_rows);
+        let j = self.num_cols;
+        for (i, element) in col.iter().enumerate() {
+            if *element != Scalar::zero() {
+                self.entries.push((i, j, *element));
+            }
+        }
+        self.num_cols += 1;
+    }
+
+    // Returns M * vec, in time O(nnz + num_rows)
+    pub fn vector_mul(&self, vec: &[Scalar]) -> Vec<Scalar> {
+        assert_eq!(vec.len(), self.num_cols);
+        let mut out = vec![Scalar::zero(); self.num_rows];
+        for (i, j, element) in self.entries.iter() {
+            out[*i] += element * vec[*j];
+        }
+        out
+    }
+
+    // Returns M^T * vec (equivalently vec^T * M), in time O(nnz + num_cols)
+    pub fn transpose_vector_mul(&self, vec: &[Scalar]) -> Vec<Scalar> {
+        assert_eq!(vec.len(), self.num_rows);
+        let mut out = vec![Scalar::zero(); self.num_cols];
+        for (i, j, element) in self.entries.iter() {
+            out[*j] += element * vec[*i];
+        }
+        out
+    }
+}
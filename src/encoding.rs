@@ -0,0 +1,237 @@
+//! Compact binary encoding of proofs.
+//!
+//! Every encoded proof starts with the magic bytes `ZKAF`, a format version
+//! and a tag naming the proof type. Group elements are written as 32-byte
+//! compressed Ristretto points, scalars as their 32-byte canonical
+//! little-endian form and vectors as a little-endian `u32` length followed by
+//! their elements. Sub-proofs are embedded without a header of their own.
+//!
+//! Decoding validates everything it reads: scalars must be canonical, points
+//! must decompress, and vector lengths are checked against the bounds of the
+//! proof type before any allocation, so untrusted bytes cannot cause panics
+//! or huge allocations.
+use crate::curve25519::errors::ProofDecodeError;
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use curve25519_dalek::ristretto::CompressedRistretto;
+
+pub const MAGIC: &[u8; 4] = b"ZKAF";
+pub const VERSION: u8 = 2;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+pub struct ProofWriter {
+    bytes: Vec<u8>,
+}
+
+impl ProofWriter {
+    pub(crate) fn new() -> Self {
+        ProofWriter { bytes: Vec::new() }
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    // A length-prefixed byte string
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_len(&mut self, len: usize) {
+        let len = u32::try_from(len).expect("vector too long to encode");
+        self.bytes.extend_from_slice(&len.to_le_bytes());
+    }
+
+    pub fn write_scalar(&mut self, scalar: &Scalar) {
+        self.bytes.extend_from_slice(&scalar.to_bytes());
+    }
+
+    pub fn write_point(&mut self, point: &CompressedGroup) {
+        self.bytes.extend_from_slice(point.as_bytes());
+    }
+
+    pub fn write_scalars(&mut self, scalars: &[Scalar]) {
+        self.write_len(scalars.len());
+        scalars.iter().for_each(|s| self.write_scalar(s));
+    }
+
+    pub fn write_points(&mut self, points: &[CompressedGroup]) {
+        self.write_len(points.len());
+        points.iter().for_each(|p| self.write_point(p));
+    }
+}
+
+pub struct ProofReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ProofReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        ProofReader { bytes }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], ProofDecodeError> {
+        if self.bytes.len() < len {
+            return Err(ProofDecodeError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn take_32(&mut self) -> Result<[u8; 32], ProofDecodeError> {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(self.take(32)?);
+        Ok(buf)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ProofDecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_len(&mut self) -> Result<usize, ProofDecodeError> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf) as usize)
+    }
+
+    pub(crate) fn read_bytes(&mut self, max: usize) -> Result<&'a [u8], ProofDecodeError> {
+        let len = self.read_len()?;
+        if len > max {
+            return Err(ProofDecodeError::VectorTooLong { len, max });
+        }
+        self.take(len)
+    }
+
+    pub fn read_scalar(&mut self) -> Result<Scalar, ProofDecodeError> {
+        let bytes = self.take_32()?;
+        Option::from(Scalar::from_bytes(&bytes)).ok_or(ProofDecodeError::NonCanonicalScalar(bytes))
+    }
+
+    pub fn read_point(&mut self) -> Result<CompressedGroup, ProofDecodeError> {
+        decode_point(&self.take_32()?)
+    }
+
+    // Reads a length prefix, rejecting lengths above `max` or beyond what the
+    // remaining bytes can hold before anything is allocated
+    fn read_bounded_len(&mut self, max: usize) -> Result<usize, ProofDecodeError> {
+        let len = self.read_len()?;
+        if len > max {
+            return Err(ProofDecodeError::VectorTooLong { len, max });
+        }
+        if len > self.bytes.len() / 32 {
+            return Err(ProofDecodeError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn read_exact_len(&mut self, expected: usize) -> Result<usize, ProofDecodeError> {
+        let found = self.read_len()?;
+        if found != expected {
+            return Err(ProofDecodeError::UnexpectedLength { expected, found });
+        }
+        if found > self.bytes.len() / 32 {
+            return Err(ProofDecodeError::UnexpectedEnd);
+        }
+        Ok(found)
+    }
+
+    pub fn read_scalars(&mut self, max: usize) -> Result<Vec<Scalar>, ProofDecodeError> {
+        let len = self.read_bounded_len(max)?;
+        (0..len).map(|_| self.read_scalar()).collect()
+    }
+
+    pub fn read_points(&mut self, max: usize) -> Result<Vec<CompressedGroup>, ProofDecodeError> {
+        let len = self.read_bounded_len(max)?;
+        (0..len).map(|_| self.read_point()).collect()
+    }
+
+    pub fn read_scalars_exact(&mut self, len: usize) -> Result<Vec<Scalar>, ProofDecodeError> {
+        let len = self.read_exact_len(len)?;
+        (0..len).map(|_| self.read_scalar()).collect()
+    }
+
+    pub fn read_points_exact(
+        &mut self,
+        len: usize,
+    ) -> Result<Vec<CompressedGroup>, ProofDecodeError> {
+        let len = self.read_exact_len(len)?;
+        (0..len).map(|_| self.read_point()).collect()
+    }
+}
+
+/// Accepts only encodings of valid Ristretto points, so that a malformed
+/// point is reported while parsing rather than when the proof is verified.
+pub fn decode_point(bytes: &[u8; 32]) -> Result<CompressedGroup, ProofDecodeError> {
+    let point = CompressedRistretto(*bytes);
+    match point.decompress() {
+        Some(_) => Ok(point),
+        None => Err(ProofDecodeError::InvalidPoint(*bytes)),
+    }
+}
+
+/// Splits a proof file as the binaries write it: the 32-byte commitment `P`
+/// followed by the encoding of the proof.
+pub fn decode_with_commitment<T: ProofEncoding>(
+    bytes: &[u8],
+) -> Result<(CompressedGroup, T), ProofDecodeError> {
+    let (P, proof) = bytes
+        .split_first_chunk::<32>()
+        .ok_or(ProofDecodeError::UnexpectedEnd)?;
+    Ok((decode_point(P)?, T::from_bytes(proof)?))
+}
+
+/// Binary encoding of a proof type. Implementors provide the body; the
+/// header handling of `to_bytes`/`from_bytes` is shared.
+pub trait ProofEncoding: Sized {
+    // Tag written after the version byte
+    const PROOF_TYPE: u8;
+
+    fn encode(&self, writer: &mut ProofWriter);
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ProofWriter {
+            bytes: Vec::with_capacity(HEADER_LEN),
+        };
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.write_u8(VERSION);
+        writer.write_u8(Self::PROOF_TYPE);
+        self.encode(&mut writer);
+        writer.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ProofDecodeError> {
+        let mut reader = ProofReader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ProofDecodeError::BadMagic);
+        }
+        let version = reader.read_u8()?;
+        if version != VERSION {
+            return Err(ProofDecodeError::UnsupportedVersion(version));
+        }
+        let found = reader.read_u8()?;
+        if found != Self::PROOF_TYPE {
+            return Err(ProofDecodeError::UnexpectedProofType {
+                expected: Self::PROOF_TYPE,
+                found,
+            });
+        }
+
+        let proof = Self::decode(&mut reader)?;
+        if !reader.bytes.is_empty() {
+            return Err(ProofDecodeError::TrailingBytes(reader.bytes.len()));
+        }
+        Ok(proof)
+    }
+}
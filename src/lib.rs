@@ -1,11 +1,14 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
+pub mod circuit;
 pub mod commitments;
 pub mod curve25519;
+pub mod encoding;
 mod math;
 mod nozk_protocol;
 mod public_parameters;
 pub mod random;
 pub mod runtime;
+pub mod statement_stream;
 pub mod transcript;
 pub mod zk_protocol;
@@ -1,3 +1,4 @@
+#![allow(dead_code)]
 pub trait Math {
   fn square_root(self) -> usize;
   fn pow2(self) -> usize;
@@ -25,7 +26,7 @@ impl Math for usize {
   /// Returns the num_bits from n in a canonical order
   fn get_bits(self, num_bits: usize) -> Vec<bool> {
     (0..num_bits)
-      .map(|shift_amount| ((self & (1 << (num_bits - shift_amount - 1))) > 0))
+      .map(|shift_amount| (self & (1 << (num_bits - shift_amount - 1))) > 0)
       .collect::<Vec<bool>>()
   }
 }
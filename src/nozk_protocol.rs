@@ -1,3 +1,3 @@
-mod bullet_proof;
+pub(crate) mod bullet_proof;
 pub mod pi_1_protocol;
 pub mod pi_2_protocol;
@@ -3,13 +3,19 @@
 #![allow(clippy::too_many_arguments)]
 use super::super::math::Math;
 use super::super::transcript::ProofTranscript;
-use crate::curve25519::errors::ProofVerifyError;
-use crate::curve25519::group::{CompressedGroup, GroupElement, VartimeMultiscalarMul};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
+};
 use crate::curve25519::scalar::Scalar;
 use crate::curve25519::scalar_math::inner_product;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
 use core::iter;
 use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::traits::Identity;
 use merlin::Transcript;
+#[cfg(feature = "multicore")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,12 +26,21 @@ pub struct BulletReductionProof {
 }
 
 impl BulletReductionProof {
+    // Verification rejects 32 or more rounds, see `verification_scalars`
+    pub const MAX_ROUNDS: usize = 31;
+
     pub fn siz(&self) -> usize {
         use std::mem;
         mem::size_of::<BulletReductionProof>()
             + mem::size_of::<CompressedGroup>() * (self.A_vec.len() + self.B_vec.len())
             + mem::size_of::<Scalar>() * self.z.len()
     }
+    /// Length the folding argument actually runs on: `n` rounded up to a power
+    /// of two, and at least 2 since the reduction stops at two entries.
+    pub fn padded_len(n: usize) -> usize {
+        n.next_power_of_two().max(2)
+    }
+
     pub fn prove(
         transcript: &mut Transcript,
         k: &GroupElement,
@@ -33,19 +48,23 @@ impl BulletReductionProof {
         z_vec: &[Scalar],
         L_vec: &[Scalar],
     ) -> BulletReductionProof {
-        let mut G = &mut G_vec.to_owned()[..];
-        let mut z = &mut z_vec.to_owned()[..];
-        let mut L = &mut L_vec.to_owned()[..];
-        let mut n = G.len();
-        assert!(n.is_power_of_two());
-        let lg_n = n.log2() - 1;
+        assert_eq!(G_vec.len(), z_vec.len());
+        assert_eq!(L_vec.len(), z_vec.len());
 
-        let G_factors: Vec<Scalar> = iter::repeat(Scalar::one()).take(n).collect();
+        // pad (G, z, L) with (identity, 0, 0) up to a power of two; the padded
+        // entries contribute nothing to either side of the folded relation
+        let mut n = BulletReductionProof::padded_len(z_vec.len());
+        let mut G_vec = G_vec.to_owned();
+        let mut z_vec = z_vec.to_owned();
+        let mut L_vec = L_vec.to_owned();
+        G_vec.resize(n, GroupElement::identity());
+        z_vec.resize(n, Scalar::zero());
+        L_vec.resize(n, Scalar::zero());
 
-        assert_eq!(G.len(), n);
-        assert_eq!(z.len(), n);
-        assert_eq!(L.len(), n);
-        assert_eq!(G_factors.len(), n);
+        let mut G = &mut G_vec[..];
+        let mut z = &mut z_vec[..];
+        let mut L = &mut L_vec[..];
+        let lg_n = n.log2() - 1;
 
         let mut A_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
         let mut B_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
@@ -56,8 +75,8 @@ impl BulletReductionProof {
             let (L_L, L_R) = L.split_at_mut(n);
             let (G_L, G_R) = G.split_at_mut(n);
 
-            let c_L = inner_product(&z_L, &L_R);
-            let c_R = inner_product(&z_R, &L_L);
+            let c_L = inner_product(z_L, L_R);
+            let c_R = inner_product(z_R, L_L);
 
             let A = GroupElement::vartime_multiscalar_mul(
                 z_L.iter().chain(iter::once(&c_L)),
@@ -74,8 +93,27 @@ impl BulletReductionProof {
 
             let c = transcript.challenge_scalar(b"c");
 
+            #[cfg(feature = "multicore")]
+            {
+                z_L.par_iter_mut()
+                    .zip(z_R.par_iter())
+                    .for_each(|(z_l, z_r)| *z_l += c * z_r);
+                L_L.par_iter_mut()
+                    .zip(L_R.par_iter())
+                    .for_each(|(l_l, l_r)| *l_l = *l_l * c + l_r);
+                G_L.par_iter_mut()
+                    .zip(G_R.par_iter())
+                    .for_each(|(g_l, g_r)| {
+                        *g_l = GroupElement::vartime_multiscalar_mul(
+                            &[c, Scalar::one()],
+                            &[*g_l, *g_r],
+                        )
+                    });
+            }
+
+            #[cfg(not(feature = "multicore"))]
             for i in 0..n {
-                z_L[i] = z_L[i] + c * z_R[i];
+                z_L[i] += c * z_R[i];
                 L_L[i] = L_L[i] * c + L_R[i];
                 G_L[i] =
                     GroupElement::vartime_multiscalar_mul(&[c, Scalar::one()], &[G_L[i], G_R[i]]);
@@ -98,20 +136,38 @@ impl BulletReductionProof {
         }
     }
 
+    /// Recomputes the round challenges and derives, in the normalized form
+    /// where `Gamma` has coefficient one, the coefficients of every `A_j`,
+    /// `B_j` and of every (padded) generator position `i` (without the final
+    /// `z[i & 1]` factor).
     fn verification_scalars(
         &self,
         n: usize,
         transcript: &mut Transcript,
     ) -> Result<(Vec<Scalar>, Vec<Scalar>, Vec<Scalar>), ProofVerifyError> {
+        // padded_len(n) is a power of two, folded down to 2 entries
+        let expected = BulletReductionProof::padded_len(n).trailing_zeros() as usize - 1;
         let lg_n = self.A_vec.len();
-        if lg_n >= 32 {
+        if lg_n != expected || lg_n > BulletReductionProof::MAX_ROUNDS {
             // 4 billion multiplications should be enough for anyone
             // and this check prevents overflow in 1<<lg_n below.
-            return Err(ProofVerifyError::InternalError);
+            return Err(ProofVerifyError::RoundCountMismatch {
+                expected,
+                found: lg_n,
+            });
         }
 
-        if n / 2 != (1 << lg_n) {
-            return Err(ProofVerifyError::InternalError);
+        if self.B_vec.len() != lg_n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: lg_n,
+                found: self.B_vec.len(),
+            });
+        }
+        if self.z.len() != 2 {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: 2,
+                found: self.z.len(),
+            });
         }
 
         // 1. Recompute x_k,...,x_1 based on the proof transcript
@@ -122,100 +178,302 @@ impl BulletReductionProof {
             challenges.push(transcript.challenge_scalar(b"c"));
         }
 
-        // 2. Compute the ML_n Distribution mentioned in
-        // <Updatable Inner Product Argument with Logarithmic Verifier and Applications>
-        // as (1,u_1,u_2,u_1u_2,u_3,u_1u_3,u_2u_3,\cdots,u_1u_2\cdots u_k)
-        let mut ml_challenges: Vec<Scalar> = Vec::with_capacity(lg_n);
-        ml_challenges.push(Scalar::one());
-        for i in (0..challenges.len()).rev() {
-            let len_ml = ml_challenges.len();
-            for j in 0..len_ml {
-                ml_challenges.push(challenges[i] * ml_challenges[j]);
-            }
+        if challenges.iter().any(|c| *c == Scalar::zero()) {
+            return Err(ProofVerifyError::ZeroChallenge);
+        }
+
+        // 2. Invert all challenges at once
+        let mut challenges_inv = challenges.clone();
+        Scalar::batch_invert(&mut challenges_inv);
+
+        // 3. Dividing the folded relation by x_1 x_2 ... x_k, round j contributes
+        // A_j / (x_1...x_j) + B_j * x_j / (x_1...x_{j-1})
+        let mut A_scalars: Vec<Scalar> = Vec::with_capacity(lg_n);
+        let mut B_scalars: Vec<Scalar> = Vec::with_capacity(lg_n);
+        let mut prefix_inv = Scalar::one();
+        for j in 0..lg_n {
+            B_scalars.push(challenges[j] * prefix_inv);
+            prefix_inv *= challenges_inv[j];
+            A_scalars.push(prefix_inv);
         }
-        // 3. Reverse of ml_challenges
-        let mut rev_ml_challenges: Vec<Scalar> = Vec::with_capacity(lg_n);
-        for i in (0..ml_challenges.len()).rev() {
-            rev_ml_challenges.push(ml_challenges[i]);
+
+        // 4. Generator G_i is folded with x_j whenever it sits in the left half
+        // of round j, so after normalization it carries the product of x_j^{-1}
+        // over the rounds where it sits in the right half. Round 1 splits on
+        // the most significant bit of i >> 1, round k on the least.
+        let mut s: Vec<Scalar> = Vec::with_capacity(1 << lg_n);
+        s.push(Scalar::one());
+        for x_inv in challenges_inv.iter().rev() {
+            let len_s = s.len();
+            for j in 0..len_s {
+                s.push(s[j] * x_inv);
+            }
         }
 
-        Ok((ml_challenges, rev_ml_challenges, challenges))
+        Ok((A_scalars, B_scalars, s))
     }
 
-    pub fn verify(
+    /// Returns the terms of the verification equation, normalized so that it
+    /// reads `Gamma + sum(scalars * points) == identity`.
+    ///
+    /// The terms are split into the coefficients of the `n` generators `G`,
+    /// the coefficient of the inner-product generator `k` and the scalars and
+    /// points for the prover's round messages `A_vec`/`B_vec`, so that callers
+    /// can merge them with their own terms into one multiscalar multiplication.
+    pub fn verification_terms(
         &self,
         n: usize,
         b: &[Scalar],
         transcript: &mut Transcript,
-        Gamma: &GroupElement,
-        Q: &GroupElement,
-        G: &[GroupElement],
-    ) -> Result<(), ProofVerifyError> {
-        assert_eq!(n, b.len());
-        let (_ml_challenges, rev_ml_challenges, challenges) =
-            self.verification_scalars(n, transcript)?;
+    ) -> Result<(Vec<Scalar>, Scalar, Vec<Scalar>, Vec<GroupElement>), ProofVerifyError> {
+        if b.len() != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: b.len(),
+            });
+        }
+        let (A_scalars, B_scalars, s) = self.verification_scalars(n, transcript)?;
 
-        let Ls = self
+        let points = self
             .A_vec
             .iter()
-            .map(|p| p.decompress().ok_or(ProofVerifyError::InternalError))
+            .chain(self.B_vec.iter())
+            .map(|p| p.unpack())
             .collect::<Result<Vec<_>, _>>()?;
+        let scalars = A_scalars.into_iter().chain(B_scalars).collect();
 
-        let Rs = self
-            .B_vec
-            .iter()
-            .map(|p| p.decompress().ok_or(ProofVerifyError::InternalError))
-            .collect::<Result<Vec<_>, _>>()?;
+        // the padded positions carry a zero b_i and the identity generator,
+        // so only the first n positions take part
+        let G_scalars: Vec<Scalar> = (0..n).map(|i| -(self.z[i & 1] * s[i >> 1])).collect();
+        let k_scalar = inner_product(&G_scalars, b);
+
+        Ok((G_scalars, k_scalar, scalars, points))
+    }
+}
+
+impl ProofEncoding for BulletReductionProof {
+    const PROOF_TYPE: u8 = 1;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_points(&self.A_vec);
+        writer.write_points(&self.B_vec);
+        writer.write_scalars(&self.z);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        let A_vec = reader.read_points(BulletReductionProof::MAX_ROUNDS)?;
+        let B_vec = reader.read_points_exact(A_vec.len())?;
+        let z = reader.read_scalars_exact(2)?;
+        Ok(BulletReductionProof { A_vec, B_vec, z })
+    }
+}
+
+/// The two-vector form of the folding argument: proves knowledge of `a`, `b`
+/// with `P = <a, G> + <b, H> + <a, b> * Q`. Each round halves both vectors,
+/// `a' = x a_L + x^{-1} a_R` and `b' = x^{-1} b_L + x b_R`, down to the
+/// single scalars `a`, `b`. Like `BulletReductionProof` it is not
+/// zero-knowledge by itself: the vectors have to be blinded beforehand.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InnerProductReductionProof {
+    L_vec: Vec<CompressedGroup>,
+    R_vec: Vec<CompressedGroup>,
+    a: Scalar,
+    b: Scalar,
+}
+
+impl InnerProductReductionProof {
+    pub fn siz(&self) -> usize {
+        use std::mem;
+        mem::size_of::<InnerProductReductionProof>()
+            + mem::size_of::<CompressedGroup>() * (self.L_vec.len() + self.R_vec.len())
+    }
+
+    /// Length the folding argument runs on: `n` rounded up to a power of two
+    pub fn padded_len(n: usize) -> usize {
+        n.next_power_of_two()
+    }
+
+    pub fn prove(
+        transcript: &mut Transcript,
+        Q: &GroupElement,
+        G_vec: &[GroupElement],
+        H_vec: &[GroupElement],
+        a_vec: &[Scalar],
+        b_vec: &[Scalar],
+    ) -> InnerProductReductionProof {
+        assert_eq!(G_vec.len(), a_vec.len());
+        assert_eq!(H_vec.len(), b_vec.len());
+        assert_eq!(a_vec.len(), b_vec.len());
+
+        // as for BulletReductionProof, zero entries over identity generators
+        // pad the statement to a power of two without changing it
+        let mut n = InnerProductReductionProof::padded_len(a_vec.len());
+        let mut G_vec = G_vec.to_owned();
+        let mut H_vec = H_vec.to_owned();
+        let mut a_vec = a_vec.to_owned();
+        let mut b_vec = b_vec.to_owned();
+        G_vec.resize(n, GroupElement::identity());
+        H_vec.resize(n, GroupElement::identity());
+        a_vec.resize(n, Scalar::zero());
+        b_vec.resize(n, Scalar::zero());
+
+        let mut G = &mut G_vec[..];
+        let mut H = &mut H_vec[..];
+        let mut a = &mut a_vec[..];
+        let mut b = &mut b_vec[..];
+        let lg_n = n.log2();
 
-        let mut b_odd: Vec<Scalar> = Vec::with_capacity(n / 2);
-        let mut b_even: Vec<Scalar> = Vec::with_capacity(n / 2);
-        for i in 0..b.len() {
-            if i % 2 == 0 {
-                b_odd.push(b[i]);
-            } else {
-                b_even.push(b[i]);
+        let mut L_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
+        let mut R_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
+
+        while n != 1 {
+            n /= 2;
+            let (a_L, a_R) = a.split_at_mut(n);
+            let (b_L, b_R) = b.split_at_mut(n);
+            let (G_L, G_R) = G.split_at_mut(n);
+            let (H_L, H_R) = H.split_at_mut(n);
+
+            let c_L = inner_product(a_L, b_R);
+            let c_R = inner_product(a_R, b_L);
+
+            let L = GroupElement::vartime_multiscalar_mul(
+                a_L.iter().chain(b_R.iter()).chain(iter::once(&c_L)),
+                G_R.iter().chain(H_L.iter()).chain(iter::once(Q)),
+            );
+
+            let R = GroupElement::vartime_multiscalar_mul(
+                a_R.iter().chain(b_L.iter()).chain(iter::once(&c_R)),
+                G_L.iter().chain(H_R.iter()).chain(iter::once(Q)),
+            );
+
+            transcript.append_point(b"L", &L.compress());
+            transcript.append_point(b"R", &R.compress());
+
+            let x = transcript.challenge_scalar(b"u");
+            let x_inv = x.invert().unwrap();
+
+            for i in 0..n {
+                a_L[i] = a_L[i] * x + a_R[i] * x_inv;
+                b_L[i] = b_L[i] * x_inv + b_R[i] * x;
+                G_L[i] = GroupElement::vartime_multiscalar_mul(&[x_inv, x], &[G_L[i], G_R[i]]);
+                H_L[i] = GroupElement::vartime_multiscalar_mul(&[x, x_inv], &[H_L[i], H_R[i]]);
             }
+
+            L_vec.push(L.compress());
+            R_vec.push(R.compress());
+
+            a = a_L;
+            b = b_L;
+            G = G_L;
+            H = H_L;
         }
-        let b_L_hat = inner_product(&b_odd, &rev_ml_challenges);
-        let b_R_hat = inner_product(&b_even, &rev_ml_challenges);
-        /*assert_eq!(self.b[0], b_L_hat);
-        assert_eq!(self.b[1], b_R_hat);*/
-
-        let mut G_odd: Vec<GroupElement> = Vec::with_capacity(n / 2);
-        let mut G_even: Vec<GroupElement> = Vec::with_capacity(n / 2);
-        for i in 0..G.len() {
-            if i % 2 == 0 {
-                G_odd.push(G[i]);
-            } else {
-                G_even.push(G[i]);
-            }
+
+        InnerProductReductionProof {
+            L_vec,
+            R_vec,
+            a: a[0],
+            b: b[0],
         }
-        let G_L_hat = GroupElement::vartime_multiscalar_mul(rev_ml_challenges.iter(), G_odd.iter());
-        let G_R_hat =
-            GroupElement::vartime_multiscalar_mul(rev_ml_challenges.iter(), G_even.iter());
-        /*assert_eq!(self.G[0], G_L_hat);
-        assert_eq!(self.G[1], G_R_hat);*/
+    }
 
-        let lg_n = self.A_vec.len();
-        let mut Gamma_hat = *Gamma;
-        for i in 0..lg_n {
-            Gamma_hat = GroupElement::vartime_multiscalar_mul(
-                &[Scalar::one(), challenges[i], challenges[i].square()],
-                &[Ls[i], Gamma_hat, Rs[i]],
-            );
+    /// Returns the terms of the verification equation, normalized so that it
+    /// reads `P + sum(scalars * points) == identity`: the coefficients of the
+    /// `n` generators `G` and `H`, of `Q`, and the scalars and points for the
+    /// round messages `L_vec`/`R_vec`.
+    pub fn verification_terms(
+        &self,
+        n: usize,
+        transcript: &mut Transcript,
+    ) -> Result<
+        (
+            Vec<Scalar>,
+            Vec<Scalar>,
+            Scalar,
+            Vec<Scalar>,
+            Vec<GroupElement>,
+        ),
+        ProofVerifyError,
+    > {
+        let padded = InnerProductReductionProof::padded_len(n);
+        let expected = padded.trailing_zeros() as usize;
+        let lg_n = self.L_vec.len();
+        if lg_n != expected || lg_n > BulletReductionProof::MAX_ROUNDS {
+            return Err(ProofVerifyError::RoundCountMismatch {
+                expected,
+                found: lg_n,
+            });
+        }
+        if self.R_vec.len() != lg_n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: lg_n,
+                found: self.R_vec.len(),
+            });
         }
 
-        let c = inner_product(&self.z, &[b_L_hat, b_R_hat]);
+        let mut challenges = Vec::with_capacity(lg_n);
+        for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+            transcript.append_point(b"L", L);
+            transcript.append_point(b"R", R);
+            challenges.push(transcript.challenge_scalar(b"u"));
+        }
+        if challenges.iter().any(|x| *x == Scalar::zero()) {
+            return Err(ProofVerifyError::ZeroChallenge);
+        }
+
+        let mut challenges_inv = challenges.clone();
+        Scalar::batch_invert(&mut challenges_inv);
+        let challenges_sq: Vec<Scalar> = challenges.iter().map(|x| x * x).collect();
+        let challenges_inv_sq: Vec<Scalar> = challenges_inv.iter().map(|x| x * x).collect();
 
-        assert_eq!(
-            Gamma_hat,
-            &GroupElement::vartime_multiscalar_mul(&self.z, &[G_L_hat, G_R_hat]) + c * Q
-        );
-        if Gamma_hat == &GroupElement::vartime_multiscalar_mul(&self.z, &[G_L_hat, G_R_hat]) + c * Q
-        {
-            Ok(())
-        } else {
-            Err(ProofVerifyError::InternalError)
+        // G_i ends up weighted by the product of x_j over the rounds where it
+        // sits in the right half and x_j^{-1} where it sits in the left half;
+        // round 1 splits on the most significant bit of i. H_i carries the
+        // inverse, which is the weight of the mirrored position n - 1 - i.
+        let mut s: Vec<Scalar> = Vec::with_capacity(padded);
+        s.push(challenges_inv.iter().product());
+        for x_sq in challenges_sq.iter().rev() {
+            let len_s = s.len();
+            for j in 0..len_s {
+                s.push(s[j] * x_sq);
+            }
         }
+
+        // only the first n positions have a generator, the padded ones are
+        // the identity
+        let G_scalars: Vec<Scalar> = (0..n).map(|i| -(self.a * s[i])).collect();
+        let H_scalars: Vec<Scalar> = (0..n).map(|i| -(self.b * s[padded - 1 - i])).collect();
+        let Q_scalar = -(self.a * self.b);
+
+        let points = self
+            .L_vec
+            .iter()
+            .chain(self.R_vec.iter())
+            .map(|p| p.unpack())
+            .collect::<Result<Vec<_>, _>>()?;
+        let scalars = challenges_sq.into_iter().chain(challenges_inv_sq).collect();
+
+        Ok((G_scalars, H_scalars, Q_scalar, scalars, points))
+    }
+}
+
+impl ProofEncoding for InnerProductReductionProof {
+    const PROOF_TYPE: u8 = 10;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_points(&self.L_vec);
+        writer.write_points(&self.R_vec);
+        writer.write_scalar(&self.a);
+        writer.write_scalar(&self.b);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        let L_vec = reader.read_points(BulletReductionProof::MAX_ROUNDS)?;
+        let R_vec = reader.read_points_exact(L_vec.len())?;
+        Ok(InnerProductReductionProof {
+            L_vec,
+            R_vec,
+            a: reader.read_scalar()?,
+            b: reader.read_scalar()?,
+        })
     }
 }
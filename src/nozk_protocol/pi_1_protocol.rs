@@ -1,17 +1,22 @@
 use super::super::commitments::MultiCommitGens;
+use crate::curve25519::errors::ProofDecodeError;
 use crate::curve25519::group::{CompressedGroup, GroupElement, VartimeMultiscalarMul};
 use crate::curve25519::scalar::Scalar;
 use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
 use crate::transcript::{AppendToTranscript, ProofTranscript};
 use merlin::Transcript;
 use serde::{Deserialize, Serialize};
 #[allow(non_camel_case_types)]
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Pi_1_Proof {}
+pub struct Pi_1_Proof {
+    pub P_hat: CompressedGroup,
+}
 
 impl Pi_1_Proof {
     pub fn siz(&self) -> usize {
-        0
+        use std::mem;
+        mem::size_of::<Pi_1_Proof>()
     }
     fn protocol_name() -> &'static [u8] {
         b"pi_1_proof"
@@ -25,7 +30,6 @@ impl Pi_1_Proof {
         l_form_vec: &[Scalar],
     ) -> (
         Pi_1_Proof,
-        CompressedGroup,
         Scalar,
         Vec<Scalar>,
         Vec<Scalar>,
@@ -36,10 +40,10 @@ impl Pi_1_Proof {
         let mut G_hat = gens_n.G.clone();
         G_hat.push(gens_n.h);
 
-        let mut z_hat = z_vec.clone().to_vec();
+        let mut z_hat = z_vec.to_vec();
         z_hat.push(*phi);
 
-        let mut L_hat = l_form_vec.clone().to_vec();
+        let mut L_hat = l_form_vec.to_vec();
         L_hat.push(Scalar::zero());
 
         let y_hat = scalar_math::compute_linearform(&L_hat, &z_hat);
@@ -53,26 +57,30 @@ impl Pi_1_Proof {
 
         let c_1 = transcript.challenge_scalar(b"c_1");
 
-        let mut L_tilde: Vec<Scalar> = Vec::new();
-        for i in 0..L_hat.len() {
-            L_tilde.push(c_1 * L_hat[i]);
-        }
+        let L_tilde = scalar_math::scalar_vector_mul(&c_1, &L_hat);
 
-        (Pi_1_Proof {}, P_hat, y_hat, L_tilde, z_hat, G_hat)
+        (Pi_1_Proof { P_hat }, y_hat, L_tilde, z_hat, G_hat)
     }
 
-    pub fn mod_verify(
-        &self,
-        transcript: &mut Transcript,
-        P_hat: &CompressedGroup,
-        y_hat: &Scalar,
-    ) -> Scalar {
+    pub fn mod_verify(&self, transcript: &mut Transcript, y_hat: &Scalar) -> Scalar {
         transcript.append_protocol_name(Pi_1_Proof::protocol_name());
-        P_hat.append_to_transcript(b"P_hat", transcript);
+        self.P_hat.append_to_transcript(b"P_hat", transcript);
         y_hat.append_to_transcript(b"y_hat", transcript);
 
-        let c_1 = transcript.challenge_scalar(b"c_1");
+        transcript.challenge_scalar(b"c_1")
+    }
+}
+
+impl ProofEncoding for Pi_1_Proof {
+    const PROOF_TYPE: u8 = 3;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.P_hat);
+    }
 
-        c_1
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_1_Proof {
+            P_hat: reader.read_point()?,
+        })
     }
 }
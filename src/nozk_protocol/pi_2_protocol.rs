@@ -1,11 +1,11 @@
+#![allow(clippy::type_complexity)]
 use super::super::commitments::MultiCommitGens;
 use super::super::transcript::{AppendToTranscript, ProofTranscript};
-use crate::curve25519::errors::ProofVerifyError;
-use crate::curve25519::group::{
-    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
-};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{CompressedGroup, GroupElement, VartimeMultiscalarMul};
 use crate::curve25519::scalar::Scalar;
 use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
 use crate::nozk_protocol::bullet_proof::BulletReductionProof;
 use merlin::Transcript;
 use serde::{Deserialize, Serialize};
@@ -23,62 +23,69 @@ impl Pi_2_Proof {
         b"pi_2 proof"
     }
 
+    // The target Q = <z_hat, G> + <L_tilde, z_hat> * k is bound into the
+    // transcript before the folding challenges
     pub fn mod_prove(
         gens_n: &MultiCommitGens,
         gens_1: &MultiCommitGens,
         transcript: &mut Transcript,
         L_tilde: &[Scalar],
         z_hat: &[Scalar],
-    ) -> (Pi_2_Proof, CompressedGroup) {
+    ) -> Pi_2_Proof {
         transcript.append_protocol_name(Pi_2_Proof::protocol_name());
 
         let n = z_hat.len();
         assert_eq!(L_tilde.len(), n);
 
         let k = &gens_1.G[0];
-        let Q = ((GroupElement::vartime_multiscalar_mul(z_hat, &gens_n.G))
+        let Q = (GroupElement::vartime_multiscalar_mul(z_hat, &gens_n.G)
             + k * scalar_math::compute_linearform(L_tilde, z_hat))
         .compress();
 
         Q.append_to_transcript(b"Q", transcript);
 
-        (
-            Pi_2_Proof {
-                bullet_reduction_proof: BulletReductionProof::prove(
-                    transcript, k, &gens_n.G, z_hat, L_tilde,
-                ),
-            },
-            Q,
-        )
+        Pi_2_Proof {
+            bullet_reduction_proof: BulletReductionProof::prove(
+                transcript, k, &gens_n.G, z_hat, L_tilde,
+            ),
+        }
     }
 
-    pub fn mod_verify(
+    // Returns the terms of `Q + sum(scalars * points) == identity` as
+    // (coefficients of the n generators, coefficient of k, scalars, points).
+    // Q itself is only appended to the transcript; the caller adds its terms.
+    pub fn verification_terms(
         &self,
         n: usize,
-        gens_n: &MultiCommitGens,
-        gens_1: &MultiCommitGens,
         transcript: &mut Transcript,
         L_tilde: &[Scalar],
         Q: &CompressedGroup,
-    ) -> Result<(), ProofVerifyError> {
-        assert!(gens_n.n >= n);
-        assert_eq!(L_tilde.len(), n);
+    ) -> Result<(Vec<Scalar>, Scalar, Vec<Scalar>, Vec<GroupElement>), ProofVerifyError> {
+        if L_tilde.len() != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: L_tilde.len(),
+            });
+        }
 
         transcript.append_protocol_name(Pi_2_Proof::protocol_name());
         Q.append_to_transcript(b"Q", transcript);
 
-        match Q.unpack() {
-            Ok(Q) => {
-                return self.bullet_reduction_proof.verify(
-                    n,
-                    &L_tilde,
-                    transcript,
-                    &Q,
-                    &gens_1.G[0],
-                    &gens_n.G,
-                );
-            }
-            Err(r) => return Err(r),
-        }
+        self.bullet_reduction_proof
+            .verification_terms(n, L_tilde, transcript)
+    }
+}
+
+impl ProofEncoding for Pi_2_Proof {
+    const PROOF_TYPE: u8 = 4;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        self.bullet_reduction_proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_2_Proof {
+            bullet_reduction_proof: BulletReductionProof::decode(reader)?,
+        })
     }
 }
@@ -0,0 +1,550 @@
+//! Streaming ingestion of affine statements.
+//!
+//! `Pi_Affine_Proof` only needs the linear form `l_vec = M^T rho`, and `rho`
+//! depends on the transcript alone, so the rows of `M` can be folded into
+//! `l_vec` as they are read and then dropped. Peak memory is O(n) instead of
+//! O(n*s).
+//!
+//! Since `rho` is derived after the statement digest is absorbed, a streamed
+//! statement is read twice: once into a `StatementDigest`, then into a
+//! `LinearFormAccumulator`. Both are `StatementSink`s, so the same reader
+//! feeds either pass.
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::curve25519::sparse_matrix::SparseMatrix;
+use digest::{ExtendableOutput, Input};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserializer;
+use sha3::Shake256;
+use std::fmt;
+use std::io::Read;
+
+/// Receives the rows of a statement with `n` columns, in order.
+pub trait StatementSink {
+    fn n(&self) -> usize;
+
+    fn num_rows(&self) -> usize;
+
+    // Rows shorter than n are padded with zeros, so trailing columns can be
+    // filled in later with `add_column`
+    fn add_row(&mut self, row: &[Scalar]);
+
+    // Appends the rows of `matrix` after the rows added so far
+    fn add_sparse(&mut self, matrix: &SparseMatrix);
+
+    // Adds column j of the first col.len() rows, which must already have
+    // been added
+    fn add_column(&mut self, j: usize, col: &[Scalar]);
+}
+
+// Accumulates l_vec = sum_i rho^i * row_i over rows pushed one at a time
+pub struct LinearFormAccumulator {
+    rho: Scalar,
+    rho_power: Scalar,
+    num_rows: usize,
+    l_vec: Vec<Scalar>,
+}
+
+impl LinearFormAccumulator {
+    pub fn new(n: usize, rho: Scalar) -> Self {
+        LinearFormAccumulator {
+            rho,
+            rho_power: Scalar::one(),
+            num_rows: 0,
+            l_vec: vec![Scalar::zero(); n],
+        }
+    }
+
+    pub fn finalize(self) -> Vec<Scalar> {
+        self.l_vec
+    }
+}
+
+impl StatementSink for LinearFormAccumulator {
+    fn n(&self) -> usize {
+        self.l_vec.len()
+    }
+
+    fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    fn add_row(&mut self, row: &[Scalar]) {
+        assert!(row.len() <= self.n());
+        for (l, m) in self.l_vec.iter_mut().zip(row.iter()) {
+            *l += self.rho_power * m;
+        }
+        self.rho_power *= self.rho;
+        self.num_rows += 1;
+    }
+
+    fn add_sparse(&mut self, matrix: &SparseMatrix) {
+        assert!(matrix.num_cols <= self.n());
+        assert!(matrix.is_well_formed());
+        if matrix.num_rows == 0 {
+            return;
+        }
+        let rho_vec = scalar_math::scalar_vector_mul(
+            &self.rho_power,
+            &scalar_math::vandemonde_challenge_one(self.rho, matrix.num_rows),
+        );
+        for (l, m) in self
+            .l_vec
+            .iter_mut()
+            .zip(matrix.transpose_vector_mul(&rho_vec))
+        {
+            *l += m;
+        }
+        self.rho_power = rho_vec[matrix.num_rows - 1] * self.rho;
+        self.num_rows += matrix.num_rows;
+    }
+
+    fn add_column(&mut self, j: usize, col: &[Scalar]) {
+        assert!(col.len() <= self.num_rows);
+        let mut power = Scalar::one();
+        for c in col {
+            self.l_vec[j] += power * c;
+            power *= self.rho;
+        }
+    }
+}
+
+/// Domain-separated digest of an affine statement `(M | b) (x, -1) = 0`
+/// over a commitment `P` to `x`: the last of the `n` columns is `b`.
+///
+/// The digest hashes `n`, the fingerprint of the generators `x` is committed
+/// under (see `DotProductProofGens::fingerprint`), `P`, the nonzero entries of
+/// each row of `M` as `(column, value)` pairs in column order, then `s` and
+/// `b`. It only depends on the statement, not on how it was supplied: dense
+/// rows, sparse entries in any order, or `b` given as a column all give the
+/// same digest. `b` is held until `finalize`, so memory is O(s).
+pub struct StatementDigest {
+    shake: Shake256,
+    n: usize,
+    b_vec: Vec<Scalar>,
+}
+
+impl StatementDigest {
+    pub fn new(n: usize, gens_fingerprint: &[u8; 32], P: &CompressedGroup) -> Self {
+        assert!(n >= 1);
+        let mut shake = Shake256::default();
+        shake.input(b"Efficient_ZKP_for_Affine-forms affine statement digest v1");
+        shake.input((n as u64).to_le_bytes());
+        shake.input(gens_fingerprint);
+        shake.input(P.as_bytes());
+        StatementDigest {
+            shake,
+            n,
+            b_vec: Vec::new(),
+        }
+    }
+
+    // `entries` are the nonzero (column, value) pairs of M in one row, in
+    // increasing column order
+    fn hash_row(&mut self, entries: &[(usize, Scalar)]) {
+        self.shake.input((entries.len() as u64).to_le_bytes());
+        for (j, value) in entries {
+            self.shake.input((*j as u64).to_le_bytes());
+            self.shake.input(value.to_bytes());
+        }
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        let mut shake = self.shake;
+        shake.input((self.b_vec.len() as u64).to_le_bytes());
+        for b in self.b_vec.iter() {
+            shake.input(b.to_bytes());
+        }
+        let mut digest = [0u8; 32];
+        shake.xof_result().read_exact(&mut digest).unwrap();
+        digest
+    }
+}
+
+impl StatementSink for StatementDigest {
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn num_rows(&self) -> usize {
+        self.b_vec.len()
+    }
+
+    fn add_row(&mut self, row: &[Scalar]) {
+        assert!(row.len() <= self.n);
+        let entries: Vec<(usize, Scalar)> = row
+            .iter()
+            .take(self.n - 1)
+            .enumerate()
+            .filter(|(_, value)| **value != Scalar::zero())
+            .map(|(j, value)| (j, *value))
+            .collect();
+        self.hash_row(&entries);
+        self.b_vec
+            .push(row.get(self.n - 1).copied().unwrap_or(Scalar::zero()));
+    }
+
+    fn add_sparse(&mut self, matrix: &SparseMatrix) {
+        assert!(matrix.num_cols <= self.n);
+        assert!(matrix.is_well_formed());
+
+        // bring the entries into row-major order, summing repeated positions
+        let mut entries = matrix.entries.clone();
+        entries.sort_unstable_by_key(|(i, j, _)| (*i, *j));
+        let mut merged: Vec<(usize, usize, Scalar)> = Vec::with_capacity(entries.len());
+        for (i, j, value) in entries {
+            match merged.last_mut() {
+                Some(last) if last.0 == i && last.1 == j => last.2 += value,
+                _ => merged.push((i, j, value)),
+            }
+        }
+
+        let mut rest = &merged[..];
+        for i in 0..matrix.num_rows {
+            let len = rest.iter().take_while(|(row, _, _)| *row == i).count();
+            let (row, tail) = rest.split_at(len);
+            rest = tail;
+
+            let mut b = Scalar::zero();
+            let mut row_entries: Vec<(usize, Scalar)> = Vec::with_capacity(row.len());
+            for (_, j, value) in row {
+                if *j == self.n - 1 {
+                    b = *value;
+                } else if *value != Scalar::zero() {
+                    row_entries.push((*j, *value));
+                }
+            }
+            self.hash_row(&row_entries);
+            self.b_vec.push(b);
+        }
+    }
+
+    // The rows of M are hashed as they arrive, so only b can be added
+    // column-wise
+    fn add_column(&mut self, j: usize, col: &[Scalar]) {
+        assert_eq!(j, self.n - 1);
+        assert!(col.len() <= self.b_vec.len());
+        for (b, c) in self.b_vec.iter_mut().zip(col.iter()) {
+            *b += c;
+        }
+    }
+}
+
+/// Streams a `raw_*.in` statement file (`m_matric` or `m_sparse`, plus
+/// `b_vec`) into `acc`, which must have one column more than the matrix: the
+/// column `b_vec` is appended as the last one, giving the `(M | b)` layout that
+/// both the homogeneous form `(M | b) (x, -1) = 0` and the native
+/// `Pi_Affine_Proof::prove_affine_streaming` expect. Rows are parsed and folded
+/// one at a time; the matrix is never held in memory.
+pub fn read_affine_statement<R: Read, S: StatementSink + ?Sized>(
+    reader: R,
+    acc: &mut S,
+) -> serde_json::Result<()> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(AffineStatementVisitor { acc })?;
+    deserializer.end()
+}
+
+struct AffineStatementVisitor<'a, S: StatementSink + ?Sized> {
+    acc: &'a mut S,
+}
+
+impl<'de, 'a, S: StatementSink + ?Sized> Visitor<'de> for AffineStatementVisitor<'a, S> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an affine statement with `m_matric` or `m_sparse`, and `b_vec`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        // the last column of acc takes b_vec
+        let num_cols = match self.acc.n().checked_sub(1) {
+            Some(num_cols) => num_cols,
+            None => return Err(de::Error::custom("the accumulator has no column for b_vec")),
+        };
+        let mut b_vec: Option<Vec<Scalar>> = None;
+        let mut has_matrix = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "m_matric" || key == "m_sparse" {
+                if has_matrix {
+                    return Err(de::Error::custom(
+                        "the statement has more than one of `m_matric` and `m_sparse`",
+                    ));
+                }
+                has_matrix = true;
+            }
+            match key.as_str() {
+                "m_matric" => map.next_value_seed(RowsSeed {
+                    acc: &mut *self.acc,
+                    num_cols,
+                })?,
+                "m_sparse" => {
+                    let matrix: SparseMatrix = map.next_value()?;
+                    if matrix.num_cols != num_cols || !matrix.is_well_formed() {
+                        return Err(de::Error::custom("m_sparse does not match n"));
+                    }
+                    self.acc.add_sparse(&matrix);
+                }
+                "b_vec" => b_vec = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let b_vec = b_vec.ok_or_else(|| de::Error::missing_field("b_vec"))?;
+        if b_vec.len() != self.acc.num_rows() {
+            return Err(de::Error::invalid_length(
+                b_vec.len(),
+                &"one entry of b_vec per matrix row",
+            ));
+        }
+        self.acc.add_column(num_cols, &b_vec);
+
+        Ok(())
+    }
+}
+
+struct RowsSeed<'a, S: StatementSink + ?Sized> {
+    acc: &'a mut S,
+    num_cols: usize,
+}
+
+impl<'de, 'a, S: StatementSink + ?Sized> DeserializeSeed<'de> for RowsSeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a, S: StatementSink + ?Sized> Visitor<'de> for RowsSeed<'a, S> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a sequence of rows of length {}", self.num_cols)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(row) = seq.next_element::<Vec<Scalar>>()? {
+            if row.len() != self.num_cols {
+                return Err(de::Error::invalid_length(row.len(), &self));
+            }
+            self.acc.add_row(&row);
+        }
+        Ok(())
+    }
+}
+
+/// The number of columns of the matrix of a `raw_*.in` statement file, for a
+/// Verifier that learns `n` from the statement itself. Only the first row of
+/// `m_matric`, or the `num_cols` of `m_sparse`, is kept; everything else is
+/// parsed and skipped, so memory is O(n).
+pub fn read_affine_num_cols<R: Read>(reader: R) -> serde_json::Result<usize> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let num_cols = deserializer.deserialize_map(NumColsVisitor)?;
+    deserializer.end()?;
+    Ok(num_cols)
+}
+
+struct NumColsVisitor;
+
+impl<'de> Visitor<'de> for NumColsVisitor {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an affine statement with `m_matric` or `m_sparse`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        let mut num_cols: Option<usize> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            let found = match key.as_str() {
+                "m_matric" => map.next_value_seed(FirstRowLen)?,
+                "m_sparse" => map.next_value_seed(SparseNumCols)?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+            };
+            if num_cols.replace(found).is_some() {
+                return Err(de::Error::custom(
+                    "the statement has more than one of `m_matric` and `m_sparse`",
+                ));
+            }
+        }
+        num_cols.ok_or_else(|| de::Error::missing_field("m_matric"))
+    }
+}
+
+// The length of the first row of a dense matrix, which must have one
+struct FirstRowLen;
+
+impl<'de> DeserializeSeed<'de> for FirstRowLen {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FirstRowLen {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a non-empty sequence of rows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let first: Vec<Scalar> = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(first.len())
+    }
+}
+
+// The `num_cols` of a sparse matrix, skipping its entries
+struct SparseNumCols;
+
+impl<'de> DeserializeSeed<'de> for SparseNumCols {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SparseNumCols {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sparse matrix with `num_cols`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        let mut num_cols: Option<usize> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "num_cols" {
+                num_cols = Some(map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        num_cols.ok_or_else(|| de::Error::missing_field("num_cols"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve25519::group::GROUP_BASEPOINT_COMPRESSED;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_statement_digest_is_representation_independent() {
+        let mut csprng: OsRng = OsRng;
+        let (n, s) = (6, 4);
+        let P = GROUP_BASEPOINT_COMPRESSED;
+
+        // (M | b) with a few zeros, including a zero entry of b
+        let mut rows: Vec<Vec<Scalar>> = (0..s)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect();
+        rows[0][2] = Scalar::zero();
+        rows[3][n - 1] = Scalar::zero();
+
+        let digest_of =
+            |fingerprint: &[u8; 32], P: &CompressedGroup, fill: &dyn Fn(&mut StatementDigest)| {
+                let mut digest = StatementDigest::new(n, fingerprint, P);
+                fill(&mut digest);
+                digest.finalize()
+            };
+
+        let dense = digest_of(&[0u8; 32], &P, &|d| {
+            rows.iter().for_each(|row| d.add_row(row))
+        });
+
+        // rows of M, then b as a column
+        let split = digest_of(&[0u8; 32], &P, &|d| {
+            rows.iter().for_each(|row| d.add_row(&row[..n - 1]));
+            let b_vec: Vec<Scalar> = rows.iter().map(|row| row[n - 1]).collect();
+            d.add_column(n - 1, &b_vec);
+        });
+        assert_eq!(split, dense);
+
+        // sparse entries in reverse order, with one value split in two
+        let mut sparse = SparseMatrix::from_dense(&rows);
+        sparse.entries.reverse();
+        let (i, j, value) = sparse.entries[0];
+        sparse.entries[0].2 = value - Scalar::one();
+        sparse.entries.push((i, j, Scalar::one()));
+        let sparse = digest_of(&[0u8; 32], &P, &|d| d.add_sparse(&sparse));
+        assert_eq!(sparse, dense);
+
+        // anything else in the statement changes the digest
+        assert_ne!(
+            digest_of(&[1u8; 32], &P, &|d| rows
+                .iter()
+                .for_each(|row| d.add_row(row))),
+            dense
+        );
+        let other_P = CompressedGroup::from_slice(&[0u8; 32]);
+        assert_ne!(
+            digest_of(&[0u8; 32], &other_P, &|d| rows
+                .iter()
+                .for_each(|row| d.add_row(row))),
+            dense
+        );
+        let extra_row = digest_of(&[0u8; 32], &P, &|d| {
+            rows.iter().for_each(|row| d.add_row(row));
+            d.add_row(&[]);
+        });
+        assert_ne!(extra_row, dense);
+        let mut changed = rows.clone();
+        changed[1][n - 1] += Scalar::one();
+        assert_ne!(
+            digest_of(&[0u8; 32], &P, &|d| changed
+                .iter()
+                .for_each(|row| d.add_row(row))),
+            dense
+        );
+    }
+
+    #[test]
+    fn check_read_affine_statement_rejects_malformed() {
+        let read = |raw: &str, n: usize| {
+            let mut acc = LinearFormAccumulator::new(n, Scalar::one());
+            read_affine_statement(raw.as_bytes(), &mut acc).map(|_| acc.finalize())
+        };
+
+        let dense = r#"{"m_matric": [["1", "2"]], "b_vec": ["3"]}"#;
+        assert_eq!(
+            read(dense, 3).unwrap(),
+            vec![Scalar::one(), Scalar::from(2u64), Scalar::from(3u64)]
+        );
+
+        // no column left for b_vec
+        assert!(read(dense, 0).is_err());
+
+        // a second matrix, in either representation
+        let both = r#"{"m_matric": [["1", "2"]],
+            "m_sparse": {"num_rows": 1, "num_cols": 2, "entries": [[0, 0, "1"]]},
+            "b_vec": ["3", "1"]}"#;
+        assert!(read(both, 3).is_err());
+        let twice = r#"{"m_matric": [["1", "2"]], "m_matric": [["1", "2"]], "b_vec": ["3", "3"]}"#;
+        assert!(read(twice, 3).is_err());
+
+        // the shape alone, from either representation
+        let num_cols = |raw: &str| read_affine_num_cols(raw.as_bytes());
+        assert_eq!(num_cols(dense).unwrap(), 2);
+        let sparse = r#"{"b_vec": ["3"],
+            "m_sparse": {"num_rows": 1, "num_cols": 4, "entries": [[0, 3, "1"]]}}"#;
+        assert_eq!(num_cols(sparse).unwrap(), 4);
+        assert!(num_cols(both).is_err());
+        assert!(num_cols(r#"{"m_matric": [], "b_vec": []}"#).is_err());
+        assert!(num_cols(r#"{"b_vec": ["3"]}"#).is_err());
+    }
+}
@@ -1,4 +1,13 @@
 mod pi_0_protocol;
 pub mod pi_a_protocol;
-mod pi_c_protocol;
+pub mod pi_c_protocol;
+pub mod pi_chain_protocol;
+pub mod pi_circuit_protocol;
+pub mod pi_hidden_protocol;
+pub mod pi_ipa_protocol;
+pub mod pi_open_protocol;
+pub mod pi_quadratic_protocol;
+pub mod pi_range_protocol;
+pub mod poly_commitment;
+mod product_phase;
 mod sigma_phase;
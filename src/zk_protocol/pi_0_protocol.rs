@@ -1,9 +1,12 @@
+#![allow(clippy::too_many_arguments)]
 use super::super::commitments::{Commitments, MultiCommitGens};
 use super::super::random::RandomTape;
 use super::super::transcript::{AppendToTranscript, ProofTranscript};
 use super::sigma_phase;
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
 use crate::curve25519::group::CompressedGroup;
 use crate::curve25519::scalar::Scalar;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
 use merlin::Transcript;
 use serde::{Deserialize, Serialize};
 
@@ -30,8 +33,16 @@ impl Pi_0_Proof {
         l_form_vec: &[Scalar],
         y: &Scalar,
         x_vec: &[Scalar],
-    ) -> (Pi_0_Proof, CompressedGroup, Vec<Scalar>, Scalar) {
-        let P = x_vec.commit(&gamma, gens_n).compress();
+        P: &CompressedGroup,
+    ) -> (Pi_0_Proof, Vec<Scalar>, Scalar) {
+        // P is supplied by the caller, who may have published it long before;
+        // a proof for the wrong one would only fail at the verifier. Checking
+        // costs an MSM of size n, so release builds skip it
+        debug_assert_eq!(
+            x_vec.commit(gamma, gens_n).compress(),
+            *P,
+            "P is not a commitment to (x, gamma)"
+        );
 
         transcript.append_protocol_name(Pi_0_Proof::protocol_name());
         P.append_to_transcript(b"P", transcript);
@@ -43,7 +54,7 @@ impl Pi_0_Proof {
 
         let (z, phi) = sigma_phase::response_phase(&c_0, x_vec, &r_vec, gamma, &rho);
 
-        (Pi_0_Proof { A, t }, P, z, phi)
+        (Pi_0_Proof { A, t }, z, phi)
     }
 
     pub fn mod_verify(
@@ -53,8 +64,13 @@ impl Pi_0_Proof {
         a: &[Scalar],
         P: &CompressedGroup,
         y: &Scalar,
-    ) -> Scalar {
-        assert_eq!(gens_n.n, a.len());
+    ) -> Result<Scalar, ProofVerifyError> {
+        if gens_n.n < a.len() {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: a.len(),
+                available: gens_n.n,
+            });
+        }
 
         transcript.append_protocol_name(Pi_0_Proof::protocol_name());
         P.append_to_transcript(b"P", transcript);
@@ -64,6 +80,22 @@ impl Pi_0_Proof {
 
         let c = transcript.challenge_scalar(b"c");
         //?为什么要返回c
-        c
+        Ok(c)
+    }
+}
+
+impl ProofEncoding for Pi_0_Proof {
+    const PROOF_TYPE: u8 = 2;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.A);
+        writer.write_scalar(&self.t);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_0_Proof {
+            A: reader.read_point()?,
+            t: reader.read_scalar()?,
+        })
     }
 }
@@ -1,47 +1,133 @@
+#![allow(clippy::too_many_arguments)]
 use super::super::random::RandomTape;
 use super::super::runtime;
 use super::super::transcript::ProofTranscript;
-use crate::commitments::DotProductProofGens;
-use crate::curve25519::errors::ProofVerifyError;
+use crate::commitments::{Commitments, DotProductProofGens};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
 use crate::curve25519::group::CompressedGroup;
 use crate::curve25519::scalar::Scalar;
 use crate::curve25519::scalar_math;
-use crate::zk_protocol::pi_c_protocol::Pi_c_Proof;
+use crate::curve25519::sparse_matrix::SparseMatrix;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::statement_stream::{LinearFormAccumulator, StatementDigest, StatementSink};
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Mode, Pi_c_Proof, ProveOptions, VerificationTerms};
 use merlin::Transcript;
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Pi_Affine_Proof {
     proof: Pi_c_Proof,
+    // y = <rho^T M, x>, the value the compressed linear form takes on x
+    y: Scalar,
 }
 
 impl Pi_Affine_Proof {
     pub fn siz(&self) -> usize {
-        self.proof.siz()
+        self.proof.siz() + std::mem::size_of::<Scalar>()
+    }
+
+    pub fn mode(&self) -> Pi_c_Mode {
+        self.proof.mode()
     }
 
     fn protocol_name() -> &'static [u8] {
         b"zk pi_affine proof"
     }
 
+    // The native relation `M x = b` is a different statement from the
+    // homogeneous `(M | b) (x, -1) = 0` even though both digest `(M | b)`
+    fn offset_protocol_name() -> &'static [u8] {
+        b"zk pi_affine offset proof"
+    }
+
+    // rho is squeezed only after the whole statement has been absorbed, so it
+    // cannot be chosen before the matrix, b, the generators or P
+    fn statement_challenge(
+        transcript: &mut Transcript,
+        protocol_name: &'static [u8],
+        digest: &[u8; 32],
+    ) -> Scalar {
+        transcript.append_protocol_name(protocol_name);
+        transcript.append_message(b"statement_digest", digest);
+        transcript.challenge_scalar(b"rho")
+    }
+
+    // A homogeneous statement `(M | b) (x, -1) = 0` claims the value 0; the
+    // proof still carries its y so that it shares the encoding of the native
+    // affine proofs
+    fn check_homogeneous(&self) -> Result<(), ProofVerifyError> {
+        if self.y != Scalar::zero() {
+            return Err(ProofVerifyError::OffsetMismatch);
+        }
+        Ok(())
+    }
+
+    fn dense_digest(
+        gens: &DotProductProofGens,
+        n: usize,
+        l_matric: &[Vec<Scalar>],
+        P: &CompressedGroup,
+    ) -> Result<[u8; 32], ProofVerifyError> {
+        let mut digest = StatementDigest::new(n, &gens.fingerprint(0..n)?, P);
+        for row in l_matric {
+            digest.add_row(row);
+        }
+        Ok(digest.finalize())
+    }
+
+    fn sparse_digest(
+        gens: &DotProductProofGens,
+        n: usize,
+        l_matric: &SparseMatrix,
+        P: &CompressedGroup,
+    ) -> Result<[u8; 32], ProofVerifyError> {
+        let mut digest = StatementDigest::new(n, &gens.fingerprint(0..n)?, P);
+        digest.add_sparse(l_matric);
+        Ok(digest.finalize())
+    }
+
+    // The commitment P = <x, G> + gamma * h that the `prove` functions create
+    // when `ProveOptions` holds none
+    pub fn commit(gens: &DotProductProofGens, x_vec: &[Scalar], gamma: &Scalar) -> CompressedGroup {
+        assert!(gens.gens_n.n >= x_vec.len());
+        x_vec
+            .commit(gamma, &gens.gens_n.prefix(x_vec.len()))
+            .compress()
+    }
+
+    /// Proves `l_matric (x) = 0` for the opening `(x, gamma)` of `P`, which
+    /// `options` may supply, see `ProveOptions`. The proof is tied to `P`
+    /// through the statement digest and verifies with `verify(.., P)`.
     pub fn prove(
         gens: &DotProductProofGens,
         transcript: &mut Transcript,
         prover_random_tape: &mut RandomTape,
         x_vec: &[Scalar],
         gamma: &Scalar,
-        l_matric: &Vec<Vec<Scalar>>,
-    ) -> (Pi_Affine_Proof, CompressedGroup, CompressedGroup, Scalar) {
+        l_matric: &[Vec<Scalar>],
+        options: &ProveOptions,
+    ) -> (Pi_Affine_Proof, CompressedGroup) {
         use std::time::Instant;
         let mut now = Instant::now();
 
-        transcript.append_protocol_name(Pi_Affine_Proof::protocol_name());
-
         let n = x_vec.len();
         let s = l_matric.len();
-        assert_eq!(gens.gens_n.n, n);
+        assert!(gens.gens_n.n >= n);
+        assert!(l_matric.iter().all(|row| row.len() == n));
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let digest = Pi_Affine_Proof::dense_digest(gens, n, l_matric, &P).unwrap();
 
-        let rho = transcript.challenge_scalar(b"rho");
+        runtime::print_runtime(&mut now, ">> ", "statement digest");
+
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest,
+        );
 
         runtime::print_runtime(&mut now, ">> ", "generate challenge rho");
 
@@ -49,7 +135,7 @@ impl Pi_Affine_Proof {
 
         runtime::print_runtime(&mut now, ">> ", "generate rho_vec");
 
-        let l_matric_t = scalar_math::matrix_transpose(&l_matric);
+        let l_matric_t = scalar_math::matrix_transpose(l_matric);
 
         runtime::print_runtime(&mut now, ">> ", "transpose");
 
@@ -57,21 +143,229 @@ impl Pi_Affine_Proof {
 
         runtime::print_runtime(&mut now, ">> ", "M * rho_vec");
 
-        let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+        let y = scalar_math::compute_linearform(&l_vec, x_vec);
 
         runtime::print_runtime(&mut now, ">> ", "compressed L_vec");
 
-        let (proof, P, P_hat) = Pi_c_Proof::prove(
-            &gens,
+        let (proof, _) = Pi_c_Proof::prove(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &l_vec,
+            &y,
+            &options.with_commitment(&P),
+        );
+
+        (Pi_Affine_Proof { proof, y }, P)
+    }
+
+    // Same protocol as `prove` (and same transcript, so either verifier accepts
+    // the proof), but rho^T M is accumulated from the nonzero entries only
+    pub fn prove_sparse(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        l_matric: &SparseMatrix,
+        options: &ProveOptions,
+    ) -> (Pi_Affine_Proof, CompressedGroup) {
+        let n = x_vec.len();
+        assert!(gens.gens_n.n >= n);
+        assert_eq!(l_matric.num_cols, n);
+        assert!(l_matric.is_well_formed());
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let digest = Pi_Affine_Proof::sparse_digest(gens, n, l_matric, &P).unwrap();
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest,
+        );
+        let rho_vec = scalar_math::vandemonde_challenge_one(rho, l_matric.num_rows);
+        let l_vec = l_matric.transpose_vector_mul(&rho_vec);
+        let y = scalar_math::compute_linearform(&l_vec, x_vec);
+
+        let (proof, _) = Pi_c_Proof::prove(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &l_vec,
+            &y,
+            &options.with_commitment(&P),
+        );
+
+        (Pi_Affine_Proof { proof, y }, P)
+    }
+
+    /// Same protocol and transcript as `prove`, but the matrix is never
+    /// materialized: `read_rows` pushes the rows into a sink, e.g. with
+    /// `|sink| statement_stream::read_affine_statement(reader, sink)`. It is
+    /// called twice, first to digest the statement and then, once rho is
+    /// known, to accumulate `M^T rho`, and must push the same rows both times.
+    pub fn prove_streaming<E>(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        mut read_rows: impl FnMut(&mut dyn StatementSink) -> Result<(), E>,
+        options: &ProveOptions,
+    ) -> Result<(Pi_Affine_Proof, CompressedGroup), E> {
+        let n = x_vec.len();
+        assert!(gens.gens_n.n >= n);
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let mut digest = StatementDigest::new(n, &gens.fingerprint(0..n).unwrap(), &P);
+        read_rows(&mut digest)?;
+        let s = digest.num_rows();
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest.finalize(),
+        );
+
+        let mut acc = LinearFormAccumulator::new(n, rho);
+        read_rows(&mut acc)?;
+        assert_eq!(acc.num_rows(), s, "the statement changed between passes");
+        let l_vec = acc.finalize();
+        let y = scalar_math::compute_linearform(&l_vec, x_vec);
+
+        let (proof, _) = Pi_c_Proof::prove(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &l_vec,
+            &y,
+            &options.with_commitment(&P),
+        );
+
+        Ok((Pi_Affine_Proof { proof, y }, P))
+    }
+
+    /// Proves `M x = b` for a public offset `b`. Unlike `prove`, `x` is passed
+    /// unmodified and `M` has no `b` column: the committed vector is exactly
+    /// `x`, and the offset is handled inside the protocol by proving
+    /// `<M^T rho, x> = <rho, b>`, whose right-hand side the verifier computes
+    /// itself. Verifies with `verify_affine`. Panics if `M x != b`.
+    pub fn prove_affine(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        m_matric: &[Vec<Scalar>],
+        b_vec: &[Scalar],
+        options: &ProveOptions,
+    ) -> (Pi_Affine_Proof, CompressedGroup) {
+        let n = x_vec.len();
+        assert_eq!(m_matric.len(), b_vec.len());
+        assert!(m_matric.iter().all(|row| row.len() == n));
+
+        Pi_Affine_Proof::prove_affine_streaming(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            |sink| {
+                for row in m_matric {
+                    sink.add_row(row);
+                }
+                sink.add_column(n, b_vec);
+                Ok::<(), Infallible>(())
+            },
+            options,
+        )
+        .unwrap_or_else(|e| match e {})
+    }
+
+    pub fn prove_affine_sparse(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        m_matric: &SparseMatrix,
+        b_vec: &[Scalar],
+        options: &ProveOptions,
+    ) -> (Pi_Affine_Proof, CompressedGroup) {
+        let n = x_vec.len();
+        assert_eq!(m_matric.num_cols, n);
+        assert_eq!(m_matric.num_rows, b_vec.len());
+        assert!(m_matric.is_well_formed());
+
+        Pi_Affine_Proof::prove_affine_streaming(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            |sink| {
+                sink.add_sparse(m_matric);
+                sink.add_column(n, b_vec);
+                Ok::<(), Infallible>(())
+            },
+            options,
+        )
+        .unwrap_or_else(|e| match e {})
+    }
+
+    /// Streaming form of `prove_affine`. `read_rows` pushes the rows of
+    /// `(M | b)`, i.e. `n + 1` columns with `b` last, which is the layout
+    /// `statement_stream::read_affine_statement` produces from a raw file.
+    pub fn prove_affine_streaming<E>(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        mut read_rows: impl FnMut(&mut dyn StatementSink) -> Result<(), E>,
+        options: &ProveOptions,
+    ) -> Result<(Pi_Affine_Proof, CompressedGroup), E> {
+        let n = x_vec.len();
+        assert!(gens.gens_n.n >= n);
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let mut digest = StatementDigest::new(n + 1, &gens.fingerprint(0..n).unwrap(), &P);
+        read_rows(&mut digest)?;
+        let s = digest.num_rows();
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::offset_protocol_name(),
+            &digest.finalize(),
+        );
+
+        let mut acc = LinearFormAccumulator::new(n + 1, rho);
+        read_rows(&mut acc)?;
+        assert_eq!(acc.num_rows(), s, "the statement changed between passes");
+        // the b column folds into <rho, b>, the value M^T rho must take on x
+        let mut l_vec = acc.finalize();
+        let y = l_vec.pop().unwrap();
+        assert_eq!(
+            scalar_math::compute_linearform(&l_vec, x_vec),
+            y,
+            "M x != b"
+        );
+
+        let (proof, _) = Pi_c_Proof::prove(
+            gens,
             transcript,
             prover_random_tape,
             x_vec,
             gamma,
             &l_vec,
             &y,
+            &options.with_commitment(&P),
         );
 
-        (Pi_Affine_Proof { proof }, P, P_hat, y)
+        Ok((Pi_Affine_Proof { proof, y }, P))
     }
 
     pub fn verify(
@@ -79,23 +373,889 @@ impl Pi_Affine_Proof {
         n: usize,
         gens: &DotProductProofGens,
         transcript: &mut Transcript,
-        l_matric: &Vec<Vec<Scalar>>,
+        l_matric: &[Vec<Scalar>],
         P: &CompressedGroup,
-        y: &Scalar,
-        P_hat: &CompressedGroup,
     ) -> Result<(), ProofVerifyError> {
-        assert!(gens.gens_n.n >= n);
+        self.verification_terms(n, gens, transcript, l_matric, P)?
+            .check(gens)
+    }
+
+    pub fn verify_sparse(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        l_matric: &SparseMatrix,
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        self.check_homogeneous()?;
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "statement has no columns".to_string(),
+            ));
+        }
+        if l_matric.num_cols != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: l_matric.num_cols,
+            });
+        }
+        if !l_matric.is_well_formed() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "sparse matrix entry out of bounds".to_string(),
+            ));
+        }
+
+        let digest = Pi_Affine_Proof::sparse_digest(gens, n, l_matric, P)?;
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest,
+        );
+        let rho_vec = scalar_math::vandemonde_challenge_one(rho, l_matric.num_rows);
+        let l_vec = l_matric.transpose_vector_mul(&rho_vec);
+
+        self.proof
+            .verify(n, gens, transcript, &l_vec, P, &Scalar::zero())
+    }
+
+    // Errors from `read_rows` are reported as `ProofVerifyError::MalformedStatement`
+    pub fn verify_streaming<E: fmt::Display>(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        mut read_rows: impl FnMut(&mut dyn StatementSink) -> Result<(), E>,
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        self.check_homogeneous()?;
+        if gens.gens_n.n < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available: gens.gens_n.n,
+            });
+        }
 
-        transcript.append_protocol_name(Pi_Affine_Proof::protocol_name());
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "statement has no columns".to_string(),
+            ));
+        }
+
+        let mut digest = StatementDigest::new(n, &gens.fingerprint(0..n)?, P);
+        read_rows(&mut digest).map_err(|e| ProofVerifyError::MalformedStatement(e.to_string()))?;
+        let s = digest.num_rows();
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest.finalize(),
+        );
+
+        let mut acc = LinearFormAccumulator::new(n, rho);
+        read_rows(&mut acc).map_err(|e| ProofVerifyError::MalformedStatement(e.to_string()))?;
+        if acc.num_rows() != s {
+            return Err(ProofVerifyError::MalformedStatement(
+                "the statement changed between passes".to_string(),
+            ));
+        }
+        let l_vec = acc.finalize();
+
+        self.proof
+            .verify(n, gens, transcript, &l_vec, P, &Scalar::zero())
+    }
+
+    /// Verifies a proof from `prove_affine` that the vector committed in `P`,
+    /// of length `n`, satisfies `M x = b`
+    pub fn verify_affine(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        m_matric: &[Vec<Scalar>],
+        b_vec: &[Scalar],
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if m_matric.is_empty() || n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "matrix has no rows or no columns".to_string(),
+            ));
+        }
+        if let Some(row) = m_matric.iter().find(|row| row.len() != n) {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: row.len(),
+            });
+        }
+        if b_vec.len() != m_matric.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: m_matric.len(),
+                found: b_vec.len(),
+            });
+        }
+
+        self.verify_affine_streaming(
+            n,
+            gens,
+            transcript,
+            |sink| {
+                for row in m_matric {
+                    sink.add_row(row);
+                }
+                sink.add_column(n, b_vec);
+                Ok::<(), Infallible>(())
+            },
+            P,
+        )
+    }
+
+    pub fn verify_affine_sparse(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        m_matric: &SparseMatrix,
+        b_vec: &[Scalar],
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if m_matric.num_cols != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: m_matric.num_cols,
+            });
+        }
+        if b_vec.len() != m_matric.num_rows {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: m_matric.num_rows,
+                found: b_vec.len(),
+            });
+        }
+        if !m_matric.is_well_formed() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "sparse matrix entry out of bounds".to_string(),
+            ));
+        }
+
+        self.verify_affine_streaming(
+            n,
+            gens,
+            transcript,
+            |sink| {
+                sink.add_sparse(m_matric);
+                sink.add_column(n, b_vec);
+                Ok::<(), Infallible>(())
+            },
+            P,
+        )
+    }
+
+    // `read_rows` pushes the rows of `(M | b)` as in `prove_affine_streaming`;
+    // its errors are reported as `ProofVerifyError::MalformedStatement`
+    pub fn verify_affine_streaming<E: fmt::Display>(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        mut read_rows: impl FnMut(&mut dyn StatementSink) -> Result<(), E>,
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if gens.gens_n.n < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available: gens.gens_n.n,
+            });
+        }
+
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "statement has no columns".to_string(),
+            ));
+        }
+
+        let mut digest = StatementDigest::new(n + 1, &gens.fingerprint(0..n)?, P);
+        read_rows(&mut digest).map_err(|e| ProofVerifyError::MalformedStatement(e.to_string()))?;
+        let s = digest.num_rows();
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::offset_protocol_name(),
+            &digest.finalize(),
+        );
+
+        let mut acc = LinearFormAccumulator::new(n + 1, rho);
+        read_rows(&mut acc).map_err(|e| ProofVerifyError::MalformedStatement(e.to_string()))?;
+        if acc.num_rows() != s {
+            return Err(ProofVerifyError::MalformedStatement(
+                "the statement changed between passes".to_string(),
+            ));
+        }
+        let mut l_vec = acc.finalize();
+        let y = l_vec.pop().unwrap();
+        if self.y != y {
+            return Err(ProofVerifyError::OffsetMismatch);
+        }
+
+        self.proof.verify(n, gens, transcript, &l_vec, P, &y)
+    }
+
+    fn verification_terms(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        l_matric: &[Vec<Scalar>],
+        P: &CompressedGroup,
+    ) -> Result<VerificationTerms, ProofVerifyError> {
+        self.check_homogeneous()?;
+        if l_matric.is_empty() || n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "matrix has no rows or no columns".to_string(),
+            ));
+        }
+        if let Some(row) = l_matric.iter().find(|row| row.len() != n) {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: row.len(),
+            });
+        }
 
         let s = l_matric.len();
-        let rho = transcript.challenge_scalar(b"rho");
+        let digest = Pi_Affine_Proof::dense_digest(gens, n, l_matric, P)?;
+        let rho = Pi_Affine_Proof::statement_challenge(
+            transcript,
+            Pi_Affine_Proof::protocol_name(),
+            &digest,
+        );
         let rho_vec = scalar_math::vandemonde_challenge_one(rho, s);
-        let l_matrix_t = scalar_math::matrix_transpose(&l_matric);
+        let l_matrix_t = scalar_math::matrix_transpose(l_matric);
         let l_vec = scalar_math::matrix_vector_mul(&l_matrix_t, &rho_vec);
 
-        return self
-            .proof
-            .verify(n, &gens, transcript, &l_vec, &P, &y, &P_hat);
+        self.proof
+            .verification_terms(n, gens, transcript, &l_vec, P, &Scalar::zero())
+    }
+
+    /// Verifies many proofs against the same `gens` at the cost of about one
+    /// multiscalar multiplication: the final equation of every proof is
+    /// weighted by a fresh random scalar and all of them are checked at once.
+    /// If the combined check fails, the batch is bisected to find the culprits,
+    /// whose positions are reported in `ProofVerifyError::BatchVerificationError`.
+    pub fn verify_batch(
+        gens: &DotProductProofGens,
+        instances: &mut [Pi_Affine_Instance],
+    ) -> Result<(), ProofVerifyError> {
+        let mut csprng: OsRng = OsRng;
+        let mut failed: Vec<usize> = Vec::new();
+        let mut weighted: Vec<(usize, VerificationTerms)> = Vec::with_capacity(instances.len());
+
+        for (i, instance) in instances.iter_mut().enumerate() {
+            match instance.proof.verification_terms(
+                instance.n,
+                gens,
+                &mut instance.transcript,
+                instance.l_matric,
+                instance.P,
+            ) {
+                Ok(terms) => {
+                    let mut scaled = VerificationTerms::default();
+                    scaled.accumulate(&terms, &Scalar::random(&mut csprng));
+                    weighted.push((i, scaled));
+                }
+                Err(_) => failed.push(i),
+            }
+        }
+
+        Pi_Affine_Proof::bisect(gens, &weighted, &mut failed);
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            failed.sort_unstable();
+            Err(ProofVerifyError::BatchVerificationError(failed))
+        }
+    }
+
+    fn bisect(
+        gens: &DotProductProofGens,
+        weighted: &[(usize, VerificationTerms)],
+        failed: &mut Vec<usize>,
+    ) {
+        if weighted.is_empty() {
+            return;
+        }
+
+        let mut combined = VerificationTerms::default();
+        for (_, terms) in weighted {
+            combined.accumulate(terms, &Scalar::one());
+        }
+        if combined.check(gens).is_ok() {
+            return;
+        }
+
+        if weighted.len() == 1 {
+            failed.push(weighted[0].0);
+            return;
+        }
+
+        let (left, right) = weighted.split_at(weighted.len() / 2);
+        Pi_Affine_Proof::bisect(gens, left, failed);
+        Pi_Affine_Proof::bisect(gens, right, failed);
+    }
+}
+
+/// One entry of `Pi_Affine_Proof::verify_batch`: the verifier transcript in
+/// the state the prover's transcript had when `prove` was called, the
+/// statement, and the proof together with the commitment returned by `prove`.
+pub struct Pi_Affine_Instance<'a> {
+    pub transcript: Transcript,
+    pub n: usize,
+    pub l_matric: &'a [Vec<Scalar>],
+    pub proof: &'a Pi_Affine_Proof,
+    pub P: &'a CompressedGroup,
+}
+
+impl ProofEncoding for Pi_Affine_Proof {
+    const PROOF_TYPE: u8 = 6;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        self.proof.encode(writer);
+        writer.write_scalar(&self.y);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Affine_Proof {
+            proof: Pi_c_Proof::decode(reader)?,
+            y: reader.read_scalar()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nozk_protocol::bullet_proof::BulletReductionProof;
+    use rand::rngs::OsRng;
+
+    fn random_statement(n: usize, s: usize) -> (Vec<Scalar>, Vec<Vec<Scalar>>) {
+        let mut csprng: OsRng = OsRng;
+        let mut x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        x_vec.push(-Scalar::one());
+
+        let m_matric = (0..s)
+            .map(|_| {
+                let mut row: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+                row.push(scalar_math::compute_linearform(&row, &x_vec[..n]));
+                row
+            })
+            .collect();
+
+        (x_vec, m_matric)
+    }
+
+    #[test]
+    fn check_pi_affine_sparse_matches_dense() {
+        let mut csprng: OsRng = OsRng;
+        let gens = DotProductProofGens::new(21, b"test-gens");
+
+        let (x_vec, mut m_matric) = random_statement(20, 4);
+        // zero out most of the matrix, keeping the statement true
+        for row in m_matric.iter_mut() {
+            for element in row[..20].iter_mut().skip(3) {
+                *element = Scalar::zero();
+            }
+            row[20] = scalar_math::compute_linearform(&row[..20], &x_vec[..20]);
+        }
+        let m_sparse = SparseMatrix::from_dense(&m_matric);
+        assert_eq!(m_sparse.nnz(), 4 * 4);
+
+        let gamma = Scalar::random(&mut csprng);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Affine_Proof::prove_sparse(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &m_sparse,
+            &ProveOptions::default(),
+        );
+        assert_eq!(proof.y, Scalar::zero());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_sparse(21, &gens, &mut verifier_transcript, &m_sparse, &P)
+            .is_ok());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(21, &gens, &mut verifier_transcript, &m_matric, &P)
+            .is_ok());
+    }
+
+    #[test]
+    fn check_pi_affine_rejects_false_homogeneous_statement() {
+        let mut csprng: OsRng = OsRng;
+        let (n, s) = (8, 3);
+        let gens = DotProductProofGens::new(n + 1, b"test-gens");
+
+        // random (M | b) and a random x, so that (M | b) (x, -1) != 0
+        let (mut x_vec, m_matric) = random_statement(n, s);
+        x_vec[0] += Scalar::one();
+        let m_sparse = SparseMatrix::from_dense(&m_matric);
+
+        let gamma = Scalar::random(&mut csprng);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Affine_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &m_matric,
+            &ProveOptions::default(),
+        );
+        assert_ne!(proof.y, Scalar::zero());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(n + 1, &gens, &mut verifier_transcript, &m_matric, &P),
+            Err(ProofVerifyError::OffsetMismatch)
+        ));
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify_sparse(n + 1, &gens, &mut verifier_transcript, &m_sparse, &P),
+            Err(ProofVerifyError::OffsetMismatch)
+        ));
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify_streaming(
+                n + 1,
+                &gens,
+                &mut verifier_transcript,
+                |sink| {
+                    sink.add_sparse(&m_sparse);
+                    Ok::<(), Infallible>(())
+                },
+                &P
+            ),
+            Err(ProofVerifyError::OffsetMismatch)
+        ));
+
+        let mut instances = [Pi_Affine_Instance {
+            transcript: Transcript::new(b"example"),
+            n: n + 1,
+            l_matric: &m_matric,
+            proof: &proof,
+            P: &P,
+        }];
+        assert!(matches!(
+            Pi_Affine_Proof::verify_batch(&gens, &mut instances),
+            Err(ProofVerifyError::BatchVerificationError(failed)) if failed == vec![0]
+        ));
+    }
+
+    #[test]
+    fn check_pi_affine_streaming() {
+        use crate::statement_stream::read_affine_statement;
+
+        let mut csprng: OsRng = OsRng;
+        let (n, s) = (12, 5);
+        let gens = DotProductProofGens::new(n + 1, b"test-gens");
+        let (x_vec, m_matric) = random_statement(n, s);
+
+        // raw_*.in layout: the matrix without its b column, then b_vec
+        let rows: Vec<Vec<Scalar>> = m_matric.iter().map(|row| row[..n].to_vec()).collect();
+        let b_vec: Vec<Scalar> = m_matric.iter().map(|row| row[n]).collect();
+        let dense_raw = serde_json::to_vec(&serde_json::json!({
+            "m_matric": rows,
+            "b_vec": b_vec,
+        }))
+        .unwrap();
+        let sparse_raw = serde_json::to_vec(&serde_json::json!({
+            "b_vec": b_vec,
+            "m_sparse": SparseMatrix::from_dense(&rows),
+        }))
+        .unwrap();
+
+        let gamma = Scalar::random(&mut csprng);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Affine_Proof::prove_streaming(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            |acc| read_affine_statement(&dense_raw[..], acc),
+            &ProveOptions::default(),
+        )
+        .unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n + 1, &gens, &mut verifier_transcript, &m_matric, &P)
+            .is_ok());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_streaming(
+                n + 1,
+                &gens,
+                &mut verifier_transcript,
+                |acc| read_affine_statement(&sparse_raw[..], acc),
+                &P,
+            )
+            .is_ok());
+
+        // a truncated file is an error, not a panic
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_streaming(
+                n + 1,
+                &gens,
+                &mut verifier_transcript,
+                |acc| read_affine_statement(&dense_raw[..dense_raw.len() / 2], acc),
+                &P,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn check_pi_affine_verify_batch() {
+        let mut csprng: OsRng = OsRng;
+        let gens = DotProductProofGens::new(40, b"test-gens");
+
+        let mut statements = Vec::new();
+        let mut proofs = Vec::new();
+        for (n, s) in [(3, 1), (10, 5), (39, 2), (16, 16), (7, 3)] {
+            let (x_vec, m_matric) = random_statement(n, s);
+            let gamma = Scalar::random(&mut csprng);
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            proofs.push(Pi_Affine_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+                &m_matric,
+                &ProveOptions::default(),
+            ));
+            statements.push(m_matric);
+        }
+
+        let instances = || -> Vec<Pi_Affine_Instance> {
+            statements
+                .iter()
+                .zip(proofs.iter())
+                .map(|(m_matric, (proof, P))| Pi_Affine_Instance {
+                    transcript: Transcript::new(b"example"),
+                    n: m_matric[0].len(),
+                    l_matric: m_matric,
+                    proof,
+                    P,
+                })
+                .collect()
+        };
+
+        assert!(Pi_Affine_Proof::verify_batch(&gens, &mut instances()).is_ok());
+
+        // swap the commitments of two proofs and check that both are reported
+        let mut instances = instances();
+        instances[1].P = &proofs[4].1;
+        instances[4].P = &proofs[1].1;
+        match Pi_Affine_Proof::verify_batch(&gens, &mut instances) {
+            Err(ProofVerifyError::BatchVerificationError(failed)) => assert_eq!(failed, vec![1, 4]),
+            _ => panic!("expected proofs 1 and 4 to fail"),
+        }
+    }
+
+    #[test]
+    fn check_pi_affine_encoding() {
+        use crate::encoding;
+
+        let mut csprng: OsRng = OsRng;
+        for (n, s) in [(1, 1), (6, 3), (7, 2), (20, 4)] {
+            let gens = DotProductProofGens::new(n + 1, b"test-gens");
+            let (x_vec, m_matric) = random_statement(n, s);
+            let gamma = Scalar::random(&mut csprng);
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, P) = Pi_Affine_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+                &m_matric,
+                &ProveOptions::default(),
+            );
+
+            let bytes = proof.to_bytes();
+
+            // 2 * ceil(log2(n + 2)) points and 4 scalars for the n + 1 entries of
+            // (x, -1), plus the header, the mode and the three vector lengths
+            let lg = (n + 2).next_power_of_two().trailing_zeros() as usize;
+            assert_eq!(bytes.len(), 6 + 1 + 3 * 4 + (2 * lg + 4) * 32);
+
+            let decoded = Pi_Affine_Proof::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.to_bytes(), bytes);
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(decoded
+                .verify(n + 1, &gens, &mut verifier_transcript, &m_matric, &P)
+                .is_ok());
+
+            let mut bad_magic = bytes.clone();
+            bad_magic[0] ^= 1;
+            assert_eq!(
+                Pi_Affine_Proof::from_bytes(&bad_magic).unwrap_err(),
+                ProofDecodeError::BadMagic
+            );
+            let mut bad_version = bytes.clone();
+            bad_version[4] = encoding::VERSION + 1;
+            assert_eq!(
+                Pi_Affine_Proof::from_bytes(&bad_version).unwrap_err(),
+                ProofDecodeError::UnsupportedVersion(encoding::VERSION + 1)
+            );
+            assert_eq!(
+                Pi_c_Proof::from_bytes(&bytes).unwrap_err(),
+                ProofDecodeError::UnexpectedProofType {
+                    expected: Pi_c_Proof::PROOF_TYPE,
+                    found: Pi_Affine_Proof::PROOF_TYPE,
+                }
+            );
+            assert_eq!(
+                Pi_Affine_Proof::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+                ProofDecodeError::UnexpectedEnd
+            );
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert_eq!(
+                Pi_Affine_Proof::from_bytes(&trailing).unwrap_err(),
+                ProofDecodeError::TrailingBytes(1)
+            );
+
+            // a proof file: P, then the proof
+            let mut file_bytes = P.as_bytes().to_vec();
+            file_bytes.extend_from_slice(&bytes);
+            let (decoded_P, decoded) =
+                encoding::decode_with_commitment::<Pi_Affine_Proof>(&file_bytes).unwrap();
+            assert_eq!(decoded_P, P);
+            assert_eq!(decoded.to_bytes(), bytes);
+            assert_eq!(
+                encoding::decode_with_commitment::<Pi_Affine_Proof>(&file_bytes[..31]).unwrap_err(),
+                ProofDecodeError::UnexpectedEnd
+            );
+        }
+    }
+
+    #[test]
+    fn check_pi_affine_decoding_rejects_malformed() {
+        let mut csprng: OsRng = OsRng;
+        let gens = DotProductProofGens::new(11, b"test-gens");
+        let (x_vec, m_matric) = random_statement(10, 5);
+        let gamma = Scalar::random(&mut csprng);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, _P) = Pi_Affine_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &m_matric,
+            &ProveOptions::default(),
+        );
+        let bytes = proof.to_bytes();
+
+        // layout: header (6), A (32), t (32), mode (1), P_hat (32), |A_vec|,
+        // A_vec, ...
+        let with = |offset: usize, patch: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + patch.len()].copy_from_slice(patch);
+            Pi_Affine_Proof::from_bytes(&bytes).unwrap_err()
+        };
+
+        assert_eq!(
+            with(6, &[0xff; 32]),
+            ProofDecodeError::InvalidPoint([0xff; 32])
+        );
+        assert_eq!(
+            with(38, &[0xff; 32]),
+            ProofDecodeError::NonCanonicalScalar([0xff; 32])
+        );
+        assert_eq!(with(70, &[2]), ProofDecodeError::UnknownMode(2));
+        assert_eq!(
+            with(71, &[0xff; 32]),
+            ProofDecodeError::InvalidPoint([0xff; 32])
+        );
+        assert_eq!(
+            with(103, &u32::MAX.to_le_bytes()),
+            ProofDecodeError::VectorTooLong {
+                len: u32::MAX as usize,
+                max: BulletReductionProof::MAX_ROUNDS,
+            }
+        );
+        // n = 11 takes 3 rounds, and B_vec must have as many entries as A_vec
+        assert_eq!(
+            with(103 + 4 + 3 * 32, &2u32.to_le_bytes()),
+            ProofDecodeError::UnexpectedLength {
+                expected: 3,
+                found: 2,
+            }
+        );
+        // a length the remaining bytes cannot hold is rejected before allocating
+        assert_eq!(
+            with(103, &20u32.to_le_bytes()),
+            ProofDecodeError::UnexpectedEnd
+        );
+    }
+
+    #[test]
+    fn check_pi_affine_existing_commitment() {
+        let mut csprng: OsRng = OsRng;
+        let n = 9;
+        let gens = DotProductProofGens::new(n + 1, b"test-gens");
+
+        // x is committed once, then proven against two unrelated statements
+        let (x_vec, m_first) = random_statement(n, 3);
+        let m_second: Vec<Vec<Scalar>> = (0..5)
+            .map(|_| {
+                let mut row: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+                row.push(scalar_math::compute_linearform(&row, &x_vec[..n]));
+                row
+            })
+            .collect();
+        let gamma = Scalar::random(&mut csprng);
+        let P = Pi_Affine_Proof::commit(&gens, &x_vec, &gamma);
+
+        for m_matric in [&m_first, &m_second] {
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, _) = Pi_Affine_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+                m_matric,
+                &ProveOptions::default().with_commitment(&P),
+            );
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n + 1, &gens, &mut verifier_transcript, m_matric, &P)
+                .is_ok());
+
+            // the proof does not carry over to another commitment to x
+            let other_P = Pi_Affine_Proof::commit(&gens, &x_vec, &Scalar::random(&mut csprng));
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n + 1, &gens, &mut verifier_transcript, m_matric, &other_P)
+                .is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "P is not a commitment to (x, gamma)")]
+    fn check_pi_affine_rejects_wrong_commitment() {
+        let n = 9;
+        let gens = DotProductProofGens::new(n + 1, b"test-gens");
+        let (x_vec, m_matric) = random_statement(n, 3);
+        let P = Pi_Affine_Proof::commit(&gens, &x_vec, &Scalar::one());
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        Pi_Affine_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &Scalar::zero(),
+            &m_matric,
+            &ProveOptions::default().with_commitment(&P),
+        );
+    }
+
+    #[test]
+    fn check_pi_affine_native_offset() {
+        use crate::statement_stream::read_affine_statement;
+
+        let mut csprng: OsRng = OsRng;
+        let (n, s) = (10, 4);
+        let gens = DotProductProofGens::new(n, b"test-gens");
+        let (mut x_vec, m_matric) = random_statement(n, s);
+        x_vec.pop();
+        let rows: Vec<Vec<Scalar>> = m_matric.iter().map(|row| row[..n].to_vec()).collect();
+        let b_vec: Vec<Scalar> = m_matric.iter().map(|row| row[n]).collect();
+        let sparse = SparseMatrix::from_dense(&rows);
+
+        let gamma = Scalar::random(&mut csprng);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Affine_Proof::prove_affine(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &rows,
+            &b_vec,
+            &ProveOptions::default(),
+        );
+        // the committed vector is x itself, without a trailing -1
+        assert_eq!(P, Pi_Affine_Proof::commit(&gens, &x_vec, &gamma));
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_affine(n, &gens, &mut verifier_transcript, &rows, &b_vec, &P)
+            .is_ok());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_affine_sparse(n, &gens, &mut verifier_transcript, &sparse, &b_vec, &P)
+            .is_ok());
+
+        let raw = serde_json::to_vec(&serde_json::json!({
+            "m_sparse": sparse,
+            "b_vec": b_vec,
+        }))
+        .unwrap();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_affine_streaming(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                |sink| read_affine_statement(&raw[..], sink),
+                &P,
+            )
+            .is_ok());
+
+        // a different offset changes <rho, b>
+        let mut wrong_b = b_vec.clone();
+        wrong_b[0] += Scalar::one();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_affine(n, &gens, &mut verifier_transcript, &rows, &wrong_b, &P)
+            .is_err());
+
+        // y is recomputed by the verifier, not taken from the proof
+        let tampered = Pi_Affine_Proof {
+            proof: Pi_c_Proof::from_bytes(&proof.proof.to_bytes()).unwrap(),
+            y: proof.y + Scalar::one(),
+        };
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            tampered.verify_affine(n, &gens, &mut verifier_transcript, &rows, &b_vec, &P),
+            Err(ProofVerifyError::OffsetMismatch)
+        ));
+
+        // the same proof is not a proof of the homogeneous statement
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &rows, &P)
+            .is_err());
     }
 }
@@ -1,33 +1,204 @@
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
-use crate::commitments::{DotProductProofGens, MultiCommitGens};
-use crate::curve25519::errors::ProofVerifyError;
-use crate::curve25519::group::{CompressedGroup, CompressedGroupExt, GROUP_BASEPOINT};
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::{Commitments, DotProductProofGens, MultiCommitGens};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul, GROUP_BASEPOINT,
+};
 use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::nozk_protocol::bullet_proof::BulletReductionProof;
 use crate::nozk_protocol::pi_1_protocol::Pi_1_Proof;
 use crate::nozk_protocol::pi_2_protocol::Pi_2_Proof;
 use crate::random::RandomTape;
 use crate::transcript::ProofTranscript;
 use crate::zk_protocol::pi_0_protocol::Pi_0_Proof;
+use curve25519_dalek::traits::Identity;
 use merlin::Transcript;
 use serde::{Deserialize, Serialize};
 
+/// Terms of a Pi_c verification equation, which holds iff they sum to the
+/// identity. The coefficients of the shared generators `G`, `h` and `k` are
+/// kept apart from the proof-specific points, so that the equations of many
+/// proofs under the same `DotProductProofGens` can be merged into one
+/// multiscalar multiplication.
+#[derive(Debug, Clone, Default)]
+pub struct VerificationTerms {
+    pub G_scalars: Vec<Scalar>,
+    pub h_scalar: Scalar,
+    pub k_scalar: Scalar,
+    pub scalars: Vec<Scalar>,
+    pub points: Vec<GroupElement>,
+}
+
+impl VerificationTerms {
+    // Adds `weight` times the terms of `other`
+    pub fn accumulate(&mut self, other: &VerificationTerms, weight: &Scalar) {
+        if self.G_scalars.len() < other.G_scalars.len() {
+            self.G_scalars.resize(other.G_scalars.len(), Scalar::zero());
+        }
+        for (acc, s) in self.G_scalars.iter_mut().zip(other.G_scalars.iter()) {
+            *acc += weight * s;
+        }
+        self.h_scalar += weight * other.h_scalar;
+        self.k_scalar += weight * other.k_scalar;
+        self.scalars
+            .extend(other.scalars.iter().map(|s| weight * s));
+        self.points.extend_from_slice(&other.points);
+    }
+
+    pub fn check(&self, gens: &DotProductProofGens) -> Result<(), ProofVerifyError> {
+        let n = self.G_scalars.len();
+        if gens.gens_n.n < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available: gens.gens_n.n,
+            });
+        }
+
+        let result = GroupElement::vartime_multiscalar_mul(
+            self.G_scalars
+                .iter()
+                .chain([self.h_scalar, self.k_scalar].iter())
+                .chain(self.scalars.iter()),
+            gens.gens_n.G[..n]
+                .iter()
+                .chain([gens.gens_n.h, gens.gens_1.G[0]].iter())
+                .chain(self.points.iter()),
+        );
+
+        if result == GroupElement::identity() {
+            Ok(())
+        } else {
+            Err(ProofVerifyError::FinalCheckFailed)
+        }
+    }
+}
+
+/// How the response of Pi_0 is sent. `Full` sends `(z, phi)` as is, i.e.
+/// `n + 1` scalars, and `Compressed` proves knowledge of it with Pi_1 and the
+/// `2 log n` points of Pi_2. The full response skips the folding rounds and
+/// is only for vectors shorter than `FULL_MODE_MAX_LEN`; `Pi_c_Proof::prove`
+/// panics on a longer one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pi_c_Mode {
+    Full,
+    Compressed,
+}
+
+impl Pi_c_Mode {
+    pub const FULL_MODE_MAX_LEN: usize = 64;
+
+    /// The mode suggested for a vector of length n
+    pub fn for_len(n: usize) -> Pi_c_Mode {
+        if n < Pi_c_Mode::FULL_MODE_MAX_LEN {
+            Pi_c_Mode::Full
+        } else {
+            Pi_c_Mode::Compressed
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Pi_c_Mode::Full => 0,
+            Pi_c_Mode::Compressed => 1,
+        }
+    }
+}
+
+/// Options shared by the `prove` functions of the proofs built on Pi_c: the
+/// `Pi_c_Mode` of the response, `Compressed` by default, and a commitment
+/// `P` the caller already holds, e.g. one published earlier, so that the
+/// same committed vector can be proven against many statements over time.
+/// Without one, `prove` commits to the vector itself; either way it returns
+/// the `P` the proof is tied to. A caller's `P` that is not a commitment to
+/// the witness makes `prove` panic in debug builds; in release builds the
+/// proof fails to verify.
+#[derive(Clone, Copy, Debug)]
+pub struct ProveOptions<'a> {
+    mode: Pi_c_Mode,
+    commitment: Option<&'a CompressedGroup>,
+}
+
+impl Default for ProveOptions<'_> {
+    fn default() -> Self {
+        ProveOptions {
+            mode: Pi_c_Mode::Compressed,
+            commitment: None,
+        }
+    }
+}
+
+impl<'a> ProveOptions<'a> {
+    pub fn with_mode(self, mode: Pi_c_Mode) -> Self {
+        ProveOptions { mode, ..self }
+    }
+
+    pub fn with_commitment(self, P: &'a CompressedGroup) -> Self {
+        ProveOptions {
+            commitment: Some(P),
+            ..self
+        }
+    }
+
+    pub fn mode(&self) -> Pi_c_Mode {
+        self.mode
+    }
+
+    pub fn commitment(&self) -> Option<&'a CompressedGroup> {
+        self.commitment
+    }
+
+    // The caller's P, or the one `commit` creates
+    pub fn commitment_or_else(&self, commit: impl FnOnce() -> CompressedGroup) -> CompressedGroup {
+        self.commitment.copied().unwrap_or_else(commit)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Pi_c_Response {
+    Full {
+        z_vec: Vec<Scalar>,
+        phi: Scalar,
+    },
+    Compressed {
+        proof_1: Pi_1_Proof,
+        proof_2: Pi_2_Proof,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Pi_c_Proof {
     proof_0: Pi_0_Proof,
-    proof_1: Pi_1_Proof,
-    proof_2: Pi_2_Proof,
+    response: Pi_c_Response,
 }
 
 impl Pi_c_Proof {
     pub fn siz(&self) -> usize {
-        self.proof_0.siz() + self.proof_1.siz() + self.proof_2.siz()
+        self.proof_0.siz()
+            + match &self.response {
+                Pi_c_Response::Full { z_vec, .. } => {
+                    (z_vec.len() + 1) * std::mem::size_of::<Scalar>()
+                }
+                Pi_c_Response::Compressed { proof_1, proof_2 } => proof_1.siz() + proof_2.siz(),
+            }
+    }
+
+    pub fn mode(&self) -> Pi_c_Mode {
+        match self.response {
+            Pi_c_Response::Full { .. } => Pi_c_Mode::Full,
+            Pi_c_Response::Compressed { .. } => Pi_c_Mode::Compressed,
+        }
     }
 
     fn protocol_name() -> &'static [u8] {
         b"zk compressed pi_c proof"
     }
 
+    /// Proves `L(x) = y` for the opening `(x, gamma)` of `P` under the first
+    /// n generators of `gens`, see `ProveOptions`
     pub fn prove(
         gens: &DotProductProofGens,
         transcript: &mut Transcript,
@@ -39,26 +210,52 @@ impl Pi_c_Proof {
         //? L
         y: &Scalar,
         //? L(y)
-    ) -> (Pi_c_Proof, CompressedGroup, CompressedGroup) {
+        options: &ProveOptions,
+    ) -> (Pi_c_Proof, CompressedGroup) {
+        assert!(gens.gens_n.n >= x_vec.len());
+        let P = options.commitment_or_else(|| {
+            x_vec
+                .commit(gamma, &gens.gens_n.prefix(x_vec.len()))
+                .compress()
+        });
+        let mode = options.mode();
+
         transcript.append_protocol_name(Pi_c_Proof::protocol_name());
+        transcript.append_u64(b"mode", mode.tag() as u64);
 
         let n = x_vec.len();
         assert_eq!(l_vec.len(), n);
-        assert_eq!(gens.gens_n.n, n);
+        assert!(gens.gens_n.n >= n);
+        assert!(
+            mode == Pi_c_Mode::Compressed || n < Pi_c_Mode::FULL_MODE_MAX_LEN,
+            "a full response is only for vectors shorter than FULL_MODE_MAX_LEN"
+        );
+
+        // a larger key serves a shorter vector through its first n generators
+        let gens_n = gens.gens_n.prefix(n);
 
-        let (proof_0, P, z_vec, phi) = Pi_0_Proof::mod_prove(
+        let (proof_0, z_vec, phi) = Pi_0_Proof::mod_prove(
             transcript,
             prover_random_tape,
-            &gens.gens_n,
+            &gens_n,
             gamma,
-            &l_vec,
-            &y,
-            &x_vec,
+            l_vec,
+            y,
+            x_vec,
+            &P,
         );
-        //? Pi_0{A, t}, P, z, phi <----- gens_n(g, h), gamma, l_vec, y, x_vec
+        //? Pi_0{A, t}, z, phi <----- gens_n(g, h), gamma, l_vec, y, x_vec, P
 
-        let (proof_1, P_hat, _y_hat, L_tilde, z_hat, G_hat_vec) =
-            Pi_1_Proof::mod_prove(transcript, &gens.gens_n, &z_vec, &phi, &l_vec);
+        if mode == Pi_c_Mode::Full {
+            let proof = Pi_c_Proof {
+                proof_0,
+                response: Pi_c_Response::Full { z_vec, phi },
+            };
+            return (proof, P);
+        }
+
+        let (proof_1, _y_hat, L_tilde, z_hat, G_hat_vec) =
+            Pi_1_Proof::mod_prove(transcript, &gens_n, &z_vec, &phi, l_vec);
 
         let gens_hat = MultiCommitGens {
             n: n + 1,
@@ -66,18 +263,13 @@ impl Pi_c_Proof {
             h: GROUP_BASEPOINT,
         };
 
-        let (proof_2, _Q) =
-            Pi_2_Proof::mod_prove(&gens_hat, &gens.gens_1, transcript, &L_tilde, &z_hat);
+        let proof_2 = Pi_2_Proof::mod_prove(&gens_hat, &gens.gens_1, transcript, &L_tilde, &z_hat);
 
-        (
-            Pi_c_Proof {
-                proof_0,
-                proof_1,
-                proof_2,
-            },
-            P,
-            P_hat,
-        )
+        let proof = Pi_c_Proof {
+            proof_0,
+            response: Pi_c_Response::Compressed { proof_1, proof_2 },
+        };
+        (proof, P)
     }
 
     pub fn verify(
@@ -88,41 +280,420 @@ impl Pi_c_Proof {
         l_vec: &[Scalar],
         P: &CompressedGroup,
         y: &Scalar,
-        P_hat: &CompressedGroup,
     ) -> Result<(), ProofVerifyError> {
-        assert!(gens.gens_n.n >= n);
-        assert_eq!(l_vec.len(), n);
+        self.verification_terms(n, gens, transcript, l_vec, P, y)?
+            .check(gens)
+    }
+
+    pub fn verification_terms(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        l_vec: &[Scalar],
+        P: &CompressedGroup,
+        y: &Scalar,
+    ) -> Result<VerificationTerms, ProofVerifyError> {
+        if gens.gens_n.n < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available: gens.gens_n.n,
+            });
+        }
+        if l_vec.len() != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: l_vec.len(),
+            });
+        }
 
         transcript.append_protocol_name(Pi_c_Proof::protocol_name());
+        transcript.append_u64(b"mode", self.mode().tag() as u64);
         let c_0 = self
             .proof_0
-            .mod_verify(&gens.gens_n, transcript, &l_vec, &P, &y);
+            .mod_verify(&gens.gens_n, transcript, l_vec, P, y)?;
 
         let y_hat = c_0 * y + self.proof_0.t;
 
-        let c_1 = self.proof_1.mod_verify(transcript, &P_hat, &y_hat);
+        let (proof_1, proof_2) = match &self.response {
+            Pi_c_Response::Full { z_vec, phi } => {
+                return self.full_verification_terms(n, l_vec, P, &c_0, &y_hat, z_vec, phi)
+            }
+            Pi_c_Response::Compressed { proof_1, proof_2 } => (proof_1, proof_2),
+        };
+
+        let c_1 = proof_1.mod_verify(transcript, &y_hat);
 
-        let mut L_hat = l_vec.clone().to_vec();
+        let mut L_hat = l_vec.to_vec();
         L_hat.push(Scalar::zero());
 
-        let mut L_tilde: Vec<Scalar> = Vec::new();
-        for i in 0..L_hat.len() {
-            L_tilde.push(c_1 * L_hat[i]);
+        let L_tilde = scalar_math::scalar_vector_mul(&c_1, &L_hat);
+
+        // Q = A + c_0 * P + c_1 * y_hat * k is evaluated for the transcript
+        // only; its terms go into the same multiscalar multiplication as the
+        // folded relation
+        let A = self.proof_0.A.unpack()?;
+        let P_point = P.unpack()?;
+        let Q = GroupElement::vartime_multiscalar_mul(
+            &[Scalar::one(), c_0, c_1 * y_hat],
+            &[A, P_point, gens.gens_1.G[0]],
+        )
+        .compress();
+
+        let (mut G_scalars, k_scalar, mut scalars, mut points) =
+            proof_2.verification_terms(n + 1, transcript, &L_tilde, &Q)?;
+
+        // the folded relation runs over G_hat = (G, h)
+        let h_scalar = G_scalars.pop().unwrap();
+
+        scalars.extend_from_slice(&[Scalar::one(), c_0]);
+        points.extend_from_slice(&[A, P_point]);
+
+        Ok(VerificationTerms {
+            G_scalars,
+            h_scalar,
+            k_scalar: c_1 * y_hat + k_scalar,
+            scalars,
+            points,
+        })
+    }
+
+    // The plain sigma protocol check: <L, z> = c_0 * y + t is a scalar
+    // equation, and <z, G> + phi * h = A + c_0 * P becomes the terms
+    fn full_verification_terms(
+        &self,
+        n: usize,
+        l_vec: &[Scalar],
+        P: &CompressedGroup,
+        c_0: &Scalar,
+        y_hat: &Scalar,
+        z_vec: &[Scalar],
+        phi: &Scalar,
+    ) -> Result<VerificationTerms, ProofVerifyError> {
+        if z_vec.len() != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: z_vec.len(),
+            });
+        }
+        if scalar_math::compute_linearform(l_vec, z_vec) != *y_hat {
+            return Err(ProofVerifyError::FinalCheckFailed);
         }
 
-        let Q = (self.proof_0.A.unpack()? + c_0 * P.unpack()? + c_1 * y_hat * gens.gens_1.G[0])
-            .compress();
+        Ok(VerificationTerms {
+            G_scalars: z_vec.to_vec(),
+            h_scalar: *phi,
+            k_scalar: Scalar::zero(),
+            scalars: vec![-Scalar::one(), -c_0],
+            points: vec![self.proof_0.A.unpack()?, P.unpack()?],
+        })
+    }
+}
 
-        let mut G_hat_vec = gens.gens_n.G.clone();
-        G_hat_vec.push(gens.gens_n.h);
-        let gens_hat = MultiCommitGens {
-            n: n + 1,
-            G: G_hat_vec,
-            h: GROUP_BASEPOINT,
+impl ProofEncoding for Pi_c_Proof {
+    const PROOF_TYPE: u8 = 5;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        self.proof_0.encode(writer);
+        writer.write_u8(self.mode().tag());
+        match &self.response {
+            Pi_c_Response::Full { z_vec, phi } => {
+                writer.write_scalars(z_vec);
+                writer.write_scalar(phi);
+            }
+            Pi_c_Response::Compressed { proof_1, proof_2 } => {
+                proof_1.encode(writer);
+                proof_2.encode(writer);
+            }
+        }
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        let proof_0 = Pi_0_Proof::decode(reader)?;
+        let response = match reader.read_u8()? {
+            0 => Pi_c_Response::Full {
+                // no longer than the vectors a compressed proof can fold
+                z_vec: reader.read_scalars(1 << BulletReductionProof::MAX_ROUNDS)?,
+                phi: reader.read_scalar()?,
+            },
+            1 => Pi_c_Response::Compressed {
+                proof_1: Pi_1_Proof::decode(reader)?,
+                proof_2: Pi_2_Proof::decode(reader)?,
+            },
+            tag => return Err(ProofDecodeError::UnknownMode(tag)),
+        };
+        Ok(Pi_c_Proof { proof_0, response })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve25519::scalar_math;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_c_proof_arbitrary_length() {
+        let mut csprng: OsRng = OsRng;
+
+        for n in [1, 2, 3, 5, 7, 8, 10, 33] {
+            let gens = DotProductProofGens::new(n, b"test-gens");
+
+            let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let l_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let gamma = Scalar::random(&mut csprng);
+            let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, P) = Pi_c_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+                &l_vec,
+                &y,
+                &ProveOptions::default(),
+            );
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n, &gens, &mut verifier_transcript, &l_vec, &P, &y)
+                .is_ok());
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            let y_wrong = y + Scalar::one();
+            assert!(matches!(
+                proof.verify(n, &gens, &mut verifier_transcript, &l_vec, &P, &y_wrong),
+                Err(ProofVerifyError::FinalCheckFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn check_pi_c_proof_full_mode() {
+        let mut csprng: OsRng = OsRng;
+
+        for n in [1, 2, 5, 8, 33] {
+            let gens = DotProductProofGens::new(n, b"test-gens");
+
+            let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let l_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let gamma = Scalar::random(&mut csprng);
+            let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+            let P = x_vec.commit(&gamma, &gens.gens_n).compress();
+
+            assert_eq!(Pi_c_Mode::for_len(n), Pi_c_Mode::Full);
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, _) = Pi_c_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+                &l_vec,
+                &y,
+                &ProveOptions::default()
+                    .with_mode(Pi_c_Mode::Full)
+                    .with_commitment(&P),
+            );
+            assert_eq!(proof.mode(), Pi_c_Mode::Full);
+
+            // A, t, the mode, then z with its length and phi
+            let bytes = proof.to_bytes();
+            assert_eq!(bytes.len(), 6 + 2 * 32 + 1 + 4 + (n + 1) * 32);
+            let decoded = Pi_c_Proof::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.mode(), Pi_c_Mode::Full);
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(decoded
+                .verify(n, &gens, &mut verifier_transcript, &l_vec, &P, &y)
+                .is_ok());
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(matches!(
+                decoded.verify(
+                    n,
+                    &gens,
+                    &mut verifier_transcript,
+                    &l_vec,
+                    &P,
+                    &(y + Scalar::one())
+                ),
+                Err(ProofVerifyError::FinalCheckFailed)
+            ));
+
+            // a z that still satisfies <L, z> but not the commitment equation
+            let mut forged = Pi_c_Proof::from_bytes(&bytes).unwrap();
+            if let Pi_c_Response::Full { z_vec, phi } = &mut forged.response {
+                *phi += Scalar::one();
+                z_vec.truncate(n);
+            }
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(matches!(
+                forged.verify(n, &gens, &mut verifier_transcript, &l_vec, &P, &y),
+                Err(ProofVerifyError::FinalCheckFailed)
+            ));
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(matches!(
+                decoded.verify(
+                    n + 1,
+                    &gens.prefix(n),
+                    &mut verifier_transcript,
+                    &l_vec,
+                    &P,
+                    &y
+                ),
+                Err(ProofVerifyError::GeneratorsTooSmall { .. })
+                    | Err(ProofVerifyError::LengthMismatch { .. })
+            ));
+        }
+
+        // the default stays compressed, and each mode only checks as itself
+        let n = 6;
+        let gens = DotProductProofGens::new(n, b"test-gens");
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let l_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_c_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &l_vec,
+            &y,
+            &ProveOptions::default(),
+        );
+        assert_eq!(proof.mode(), Pi_c_Mode::Compressed);
+        assert_eq!(Pi_c_Mode::for_len(64), Pi_c_Mode::Compressed);
+
+        let mut bytes = proof.to_bytes();
+        assert_eq!(bytes[6 + 64], 1);
+        bytes[6 + 64] = 0;
+        assert!(Pi_c_Proof::from_bytes(&bytes).map_or(true, |proof| {
+            let mut verifier_transcript = Transcript::new(b"example");
+            proof
+                .verify(n, &gens, &mut verifier_transcript, &l_vec, &P, &y)
+                .is_err()
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "a full response is only for vectors shorter than FULL_MODE_MAX_LEN")]
+    fn check_pi_c_proof_full_mode_rejects_long_vectors() {
+        let n = Pi_c_Mode::FULL_MODE_MAX_LEN;
+        let gens = DotProductProofGens::new(n, b"test-gens");
+        let x_vec = vec![Scalar::one(); n];
+        let l_vec = vec![Scalar::one(); n];
+        let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        Pi_c_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &Scalar::one(),
+            &l_vec,
+            &y,
+            &ProveOptions::default().with_mode(Pi_c_Mode::Full),
+        );
+    }
+
+    #[test]
+    fn check_pi_c_proof_with_larger_key() {
+        let mut csprng: OsRng = OsRng;
+        let n = 6;
+        let large = DotProductProofGens::new(40, b"test-gens");
+        let exact = DotProductProofGens::new(n, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let l_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+
+        // committed and proven under the large key, checked under the exact
+        // one and under the large key itself
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_c_Proof::prove(
+            &large,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &l_vec,
+            &y,
+            &ProveOptions::default(),
+        );
+        assert_eq!(P, x_vec.commit(&gamma, &exact.gens_n).compress());
+
+        for gens in [&exact, &large, &large.prefix(n)] {
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n, gens, &mut verifier_transcript, &l_vec, &P, &y)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn check_pi_c_proof_rejects_without_panicking() {
+        let mut csprng: OsRng = OsRng;
+        let n = 6;
+        let gens = DotProductProofGens::new(n, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let l_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let y = scalar_math::compute_linearform(&l_vec, &x_vec);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_c_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &l_vec,
+            &y,
+            &ProveOptions::default(),
+        );
+
+        let verify = |n: usize, gens: &DotProductProofGens, l_vec: &[Scalar]| {
+            let mut verifier_transcript = Transcript::new(b"example");
+            proof.verify(n, gens, &mut verifier_transcript, l_vec, &P, &y)
         };
 
-        return self
-            .proof_2
-            .mod_verify(n + 1, &gens_hat, &gens.gens_1, transcript, &L_tilde, &Q);
+        assert!(matches!(
+            verify(n, &gens, &l_vec[..n - 1]),
+            Err(ProofVerifyError::LengthMismatch {
+                expected: 6,
+                found: 5
+            })
+        ));
+        assert!(matches!(
+            verify(n, &DotProductProofGens::new(n - 1, b"test-gens"), &l_vec),
+            Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: 6,
+                available: 5
+            })
+        ));
+        // n = 6 pads to 8 entries (with h), i.e. 2 rounds; n = 2 expects 1
+        assert!(matches!(
+            verify(2, &gens, &l_vec[..2]),
+            Err(ProofVerifyError::RoundCountMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
     }
 }
@@ -0,0 +1,712 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::DotProductProofGens;
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::random::RandomTape;
+use crate::statement_stream::{StatementDigest, StatementSink};
+use crate::transcript::ProofTranscript;
+use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Proof, ProveOptions};
+use crate::zk_protocol::pi_hidden_protocol::{
+    bind_stacked, prove_stacked, stacked_challenges, verify_stacked, Pi_Hidden_Output_Proof,
+};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Proof for a chain of public linear maps applied to a committed `x`:
+/// `y_1 = M_1 x`, `y_i = M_i y_{i-1}`, and a final public relation
+/// `N y_k = c`. The intermediate vectors stay private; the verifier sees
+/// `P`, the maps, `(N, c)` and the proof.
+///
+/// The stages are stacked into `w = (x, y_1, ..., y_k)`: the intermediates
+/// are committed right after `x` in `C_rest`, which the proof carries, and
+/// every stage becomes a block of rows of one affine statement over `w`.
+/// As in `Pi_Hidden_Output_Proof`, a challenge `e` reduces it to a single
+/// Pi_c proof over `P + e * C_rest`, which the prover can only answer
+/// knowing the openings of `P` and `C_rest` separately.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Chain_Proof {
+    C_rest: CompressedGroup,
+    proof: Pi_c_Proof,
+}
+
+impl Pi_Chain_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_chain proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        std::mem::size_of::<CompressedGroup>() + self.proof.siz()
+    }
+
+    // Lengths of x, y_1, ..., y_k, or the first inconsistency between
+    // consecutive maps and the final relation
+    fn block_lengths(
+        n: usize,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+    ) -> Result<Vec<usize>, ProofVerifyError> {
+        if n == 0 || maps.is_empty() || n_matric.is_empty() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "a chain needs an input, at least one map and a final relation".to_string(),
+            ));
+        }
+
+        let mut lengths = vec![n];
+        for matrix in maps
+            .iter()
+            .map(Vec::as_slice)
+            .chain(std::iter::once(n_matric))
+        {
+            let cols = *lengths.last().unwrap();
+            if let Some(row) = matrix.iter().find(|row| row.len() != cols) {
+                return Err(ProofVerifyError::LengthMismatch {
+                    expected: cols,
+                    found: row.len(),
+                });
+            }
+            lengths.push(matrix.len());
+        }
+        if c_vec.len() != n_matric.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n_matric.len(),
+                found: c_vec.len(),
+            });
+        }
+
+        // the last entry is the number of final relations, not a block of w
+        lengths.pop();
+        if lengths[1..].contains(&0) {
+            return Err(ProofVerifyError::MalformedStatement(
+                "a map has no rows".to_string(),
+            ));
+        }
+        Ok(lengths)
+    }
+
+    // `lengths` are those of x, y_1, ..., y_k
+    fn statement_challenges(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        lengths: &[usize],
+        P: &CompressedGroup,
+        C_rest: &CompressedGroup,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+    ) -> Result<(Scalar, Scalar), ProofVerifyError> {
+        let gens_fingerprint = gens.fingerprint(0..lengths[0])?;
+        transcript.append_protocol_name(Pi_Chain_Proof::protocol_name());
+        transcript.append_u64(b"stages", maps.len() as u64);
+        for matrix in maps {
+            // a map has no offset, so its b column stays zero
+            let mut digest = StatementDigest::new(matrix[0].len() + 1, &gens_fingerprint, P);
+            for row in matrix {
+                digest.add_row(row);
+            }
+            transcript.append_message(b"stage_digest", &digest.finalize());
+        }
+
+        let mut digest = StatementDigest::new(n_matric[0].len() + 1, &gens_fingerprint, P);
+        for row in n_matric {
+            digest.add_row(row);
+        }
+        digest.add_column(n_matric[0].len(), c_vec);
+        transcript.append_message(b"statement_digest", &digest.finalize());
+
+        bind_stacked(
+            gens,
+            transcript,
+            lengths[0],
+            lengths[1..].iter().sum(),
+            C_rest,
+        )?;
+        stacked_challenges(transcript)
+    }
+
+    // The rows of the stacked statement are those of M_1, ..., M_k, N in this
+    // order, weighted by consecutive powers of rho. Returns the form on
+    // (x, e * y_1, ..., e * y_k) and its value e * <rho_N, c>.
+    fn combined_form(
+        e: &Scalar,
+        rho: &Scalar,
+        lengths: &[usize],
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+    ) -> (Vec<Scalar>, Scalar) {
+        let total: usize = lengths.iter().sum();
+        let num_rows = lengths[1..].iter().sum::<usize>() + c_vec.len();
+        let rho_vec = scalar_math::vandemonde_challenge_one(*rho, num_rows);
+
+        let mut l_vec = vec![Scalar::zero(); total];
+        let (mut row, mut col) = (0, 0);
+        for (i, matrix) in maps.iter().enumerate() {
+            let weights = &rho_vec[row..row + matrix.len()];
+            let m_t_rho =
+                scalar_math::matrix_vector_mul(&scalar_math::matrix_transpose(matrix), weights);
+            for (l, m) in l_vec[col..col + lengths[i]].iter_mut().zip(m_t_rho) {
+                *l += m;
+            }
+            // y_{i+1} enters its own rows with coefficient -1
+            let next = col + lengths[i];
+            for (l, w) in l_vec[next..next + matrix.len()].iter_mut().zip(weights) {
+                *l -= w;
+            }
+            row += matrix.len();
+            col = next;
+        }
+
+        let weights = &rho_vec[row..];
+        let n_t_rho =
+            scalar_math::matrix_vector_mul(&scalar_math::matrix_transpose(n_matric), weights);
+        for (l, m) in l_vec[col..].iter_mut().zip(n_t_rho) {
+            *l += m;
+        }
+        let y = scalar_math::compute_linearform(weights, c_vec);
+
+        // (l_x, l_rest) on (x, rest) is (e * l_x, l_rest) on (x, e * rest)
+        for l in l_vec[..lengths[0]].iter_mut() {
+            *l *= e;
+        }
+        (l_vec, e * y)
+    }
+
+    /// Proves the chain for the opening `(x, gamma)` of `P`, which `options`
+    /// may supply. The intermediates are computed here. Panics unless
+    /// `N y_k = c` holds.
+    pub fn prove(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+        options: &ProveOptions,
+    ) -> (Pi_Chain_Proof, CompressedGroup) {
+        let lengths = Pi_Chain_Proof::block_lengths(x_vec.len(), maps, n_matric, c_vec).unwrap();
+        let total: usize = lengths.iter().sum();
+        assert!(gens.gens_n.n >= total);
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let mut rest: Vec<Scalar> = Vec::with_capacity(total - x_vec.len());
+        let mut y_vec = x_vec.to_vec();
+        for matrix in maps {
+            y_vec = scalar_math::matrix_vector_mul(matrix, &y_vec);
+            rest.extend_from_slice(&y_vec);
+        }
+        assert_eq!(
+            scalar_math::matrix_vector_mul(n_matric, &y_vec),
+            c_vec,
+            "N y_k != c"
+        );
+
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let C_rest = Pi_Hidden_Output_Proof::commit_output(gens, x_vec.len(), &rest, &delta);
+
+        let (e, rho) = Pi_Chain_Proof::statement_challenges(
+            gens, transcript, &lengths, &P, &C_rest, maps, n_matric, c_vec,
+        )
+        .unwrap();
+        let (l_vec, y) = Pi_Chain_Proof::combined_form(&e, &rho, &lengths, maps, n_matric, c_vec);
+
+        let proof = prove_stacked(
+            gens,
+            transcript,
+            prover_random_tape,
+            options.mode(),
+            x_vec,
+            gamma,
+            &P,
+            &rest,
+            &delta,
+            &C_rest,
+            &e,
+            &l_vec,
+            &y,
+        );
+
+        (Pi_Chain_Proof { C_rest, proof }, P)
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        let lengths = Pi_Chain_Proof::block_lengths(n, maps, n_matric, c_vec)?;
+        let total: usize = lengths.iter().sum();
+        if gens.gens_n.n < total {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: total,
+                available: gens.gens_n.n,
+            });
+        }
+
+        let (e, rho) = Pi_Chain_Proof::statement_challenges(
+            gens,
+            transcript,
+            &lengths,
+            P,
+            &self.C_rest,
+            maps,
+            n_matric,
+            c_vec,
+        )?;
+        let (l_vec, y) = Pi_Chain_Proof::combined_form(&e, &rho, &lengths, maps, n_matric, c_vec);
+
+        verify_stacked(
+            &self.proof,
+            gens,
+            transcript,
+            P,
+            &self.C_rest,
+            &e,
+            &l_vec,
+            &y,
+        )
+    }
+}
+
+impl ProofEncoding for Pi_Chain_Proof {
+    const PROOF_TYPE: u8 = 9;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.C_rest);
+        self.proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Chain_Proof {
+            C_rest: reader.read_point()?,
+            proof: Pi_c_Proof::decode(reader)?,
+        })
+    }
+}
+
+/// The chain of `Pi_Chain_Proof` proven stage by stage, with a commitment to
+/// every intermediate. `C_i` commits to `y_i` under the generators right after
+/// those of `y_{i-1}` (with `y_0 = x` and `C_0 = P`), so it is both the output
+/// commitment of stage `i` and the input commitment of stage `i + 1`. Stage
+/// `i` is a `Pi_Hidden_Output_Proof` that `C_i` commits to `M_i y_{i-1}` under
+/// the key shifted to `y_{i-1}` (`DotProductProofGens::suffix`), and
+/// `N y_k = c` is a `Pi_Affine_Proof` over `C_k`, all in one transcript.
+///
+/// The proof carries `C_1, ..., C_k`, which hide the intermediates, and one
+/// Pi_c proof per stage, so it is larger than `Pi_Chain_Proof`. In exchange,
+/// each stage can be proven and checked on its own with `prove_stage` and
+/// `verify_stage`, and its commitment can be handed on to further proofs.
+/// The key needs as many generators as the stacked vector.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Staged_Chain_Proof {
+    commitments: Vec<CompressedGroup>,
+    stages: Vec<Pi_Hidden_Output_Proof>,
+    last: Pi_Affine_Proof,
+}
+
+impl Pi_Staged_Chain_Proof {
+    // bounds the number of stages read from untrusted bytes
+    const MAX_STAGES: usize = 1 << 10;
+
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_staged_chain proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        self.commitments.len() * std::mem::size_of::<CompressedGroup>()
+            + self.stages.iter().map(|stage| stage.siz()).sum::<usize>()
+            + self.last.siz()
+    }
+
+    /// The intermediate commitments `C_1, ..., C_k`
+    pub fn commitments(&self) -> &[CompressedGroup] {
+        &self.commitments
+    }
+
+    fn append_statement(transcript: &mut Transcript, n: usize, stages: usize) {
+        transcript.append_protocol_name(Pi_Staged_Chain_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        transcript.append_u64(b"stages", stages as u64);
+    }
+
+    /// Proves the stage `y_out = M y_in` for the opening `(y_in, gamma_in)` of
+    /// `C_in`, whose entries start at `offset` in the stacked vector; `options`
+    /// supplies `C_in` like the `P` of other proofs. Returns the proof,
+    /// `y_out`, and `C_out` with its blinding, which the next stage takes as
+    /// its input at `offset + y_in.len()`.
+    pub fn prove_stage(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        offset: usize,
+        y_in: &[Scalar],
+        gamma_in: &Scalar,
+        matrix: &[Vec<Scalar>],
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, Vec<Scalar>, Scalar, CompressedGroup) {
+        let gens = gens.suffix(offset);
+        let y_out = scalar_math::matrix_vector_mul(matrix, y_in);
+        let gamma_out = prover_random_tape.random_scalar(b"delta");
+        let C_out = Pi_Hidden_Output_Proof::commit_output(&gens, y_in.len(), &y_out, &gamma_out);
+        let (proof, _) = Pi_Hidden_Output_Proof::prove_with_output(
+            &gens,
+            transcript,
+            prover_random_tape,
+            y_in,
+            gamma_in,
+            &gamma_out,
+            &C_out,
+            matrix,
+            options,
+        );
+        (proof, y_out, gamma_out, C_out)
+    }
+
+    /// Checks a `prove_stage` proof that `C_out` commits to `M y_in` for the
+    /// `y_in` of length `n_in` committed in `C_in` at `offset`
+    pub fn verify_stage(
+        proof: &Pi_Hidden_Output_Proof,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        offset: usize,
+        n_in: usize,
+        C_in: &CompressedGroup,
+        matrix: &[Vec<Scalar>],
+        C_out: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if gens.gens_n.n < offset {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: offset,
+                available: gens.gens_n.n,
+            });
+        }
+        proof.verify(n_in, &gens.suffix(offset), transcript, matrix, C_in, C_out)
+    }
+
+    /// Proves the chain stage by stage for the opening `(x, gamma)` of `P`,
+    /// which `options` may supply. Panics unless `N y_k = c` holds.
+    pub fn prove(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+        options: &ProveOptions,
+    ) -> (Pi_Staged_Chain_Proof, CompressedGroup) {
+        let lengths = Pi_Chain_Proof::block_lengths(x_vec.len(), maps, n_matric, c_vec).unwrap();
+        assert!(gens.gens_n.n >= lengths.iter().sum());
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+        Pi_Staged_Chain_Proof::append_statement(transcript, x_vec.len(), maps.len());
+
+        let (mut y_vec, mut gamma_y, mut C_y) = (x_vec.to_vec(), *gamma, P);
+        let mut offset = 0;
+        let mut commitments = Vec::with_capacity(maps.len());
+        let mut stages = Vec::with_capacity(maps.len());
+        for matrix in maps {
+            let (stage, y_out, gamma_out, C_out) = Pi_Staged_Chain_Proof::prove_stage(
+                gens,
+                transcript,
+                prover_random_tape,
+                offset,
+                &y_vec,
+                &gamma_y,
+                matrix,
+                &options.with_commitment(&C_y),
+            );
+            offset += y_vec.len();
+            stages.push(stage);
+            commitments.push(C_out);
+            (y_vec, gamma_y, C_y) = (y_out, gamma_out, C_out);
+        }
+
+        let (last, _) = Pi_Affine_Proof::prove_affine(
+            &gens.suffix(offset),
+            transcript,
+            prover_random_tape,
+            &y_vec,
+            &gamma_y,
+            n_matric,
+            c_vec,
+            &options.with_commitment(&C_y),
+        );
+
+        let proof = Pi_Staged_Chain_Proof {
+            commitments,
+            stages,
+            last,
+        };
+        (proof, P)
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        maps: &[Vec<Vec<Scalar>>],
+        n_matric: &[Vec<Scalar>],
+        c_vec: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        let lengths = Pi_Chain_Proof::block_lengths(n, maps, n_matric, c_vec)?;
+        let total: usize = lengths.iter().sum();
+        if gens.gens_n.n < total {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: total,
+                available: gens.gens_n.n,
+            });
+        }
+        if self.stages.len() != maps.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: maps.len(),
+                found: self.stages.len(),
+            });
+        }
+        if self.commitments.len() != maps.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: maps.len(),
+                found: self.commitments.len(),
+            });
+        }
+        Pi_Staged_Chain_Proof::append_statement(transcript, n, maps.len());
+
+        let (mut C_y, mut offset) = (*P, 0);
+        for (i, (matrix, (stage, C_out))) in maps
+            .iter()
+            .zip(self.stages.iter().zip(self.commitments.iter()))
+            .enumerate()
+        {
+            Pi_Staged_Chain_Proof::verify_stage(
+                stage, gens, transcript, offset, lengths[i], &C_y, matrix, C_out,
+            )?;
+            offset += lengths[i];
+            C_y = *C_out;
+        }
+
+        self.last.verify_affine(
+            lengths[maps.len()],
+            &gens.suffix(offset),
+            transcript,
+            n_matric,
+            c_vec,
+            &C_y,
+        )
+    }
+}
+
+impl ProofEncoding for Pi_Staged_Chain_Proof {
+    const PROOF_TYPE: u8 = 15;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_points(&self.commitments);
+        for stage in self.stages.iter() {
+            stage.encode(writer);
+        }
+        self.last.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        let commitments = reader.read_points(Pi_Staged_Chain_Proof::MAX_STAGES)?;
+        let stages = (0..commitments.len())
+            .map(|_| Pi_Hidden_Output_Proof::decode(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Pi_Staged_Chain_Proof {
+            commitments,
+            stages,
+            last: Pi_Affine_Proof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn random_matrix(rows: usize, cols: usize) -> Vec<Vec<Scalar>> {
+        let mut csprng: OsRng = OsRng;
+        (0..rows)
+            .map(|_| (0..cols).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn check_pi_chain_proof() {
+        let mut csprng: OsRng = OsRng;
+        let n = 8;
+        let gens = DotProductProofGens::new(n + 5 + 3, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let maps = vec![random_matrix(5, n), random_matrix(3, 5)];
+        let n_matric = random_matrix(2, 3);
+        let z_vec = scalar_math::matrix_vector_mul(
+            &maps[1],
+            &scalar_math::matrix_vector_mul(&maps[0], &x_vec),
+        );
+        let c_vec = scalar_math::matrix_vector_mul(&n_matric, &z_vec);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Chain_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &maps,
+            &n_matric,
+            &c_vec,
+            &ProveOptions::default(),
+        );
+        let proof = Pi_Chain_Proof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let verify = |maps: &[Vec<Vec<Scalar>>], c_vec: &[Scalar]| {
+            let mut verifier_transcript = Transcript::new(b"example");
+            proof.verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &P,
+                maps,
+                &n_matric,
+                c_vec,
+            )
+        };
+        assert!(verify(&maps, &c_vec).is_ok());
+
+        let mut wrong_c = c_vec.clone();
+        wrong_c[0] += Scalar::one();
+        assert!(matches!(
+            verify(&maps, &wrong_c),
+            Err(ProofVerifyError::FinalCheckFailed)
+        ));
+
+        let mut wrong_maps = maps.clone();
+        wrong_maps[1][0][0] += Scalar::one();
+        assert!(verify(&wrong_maps, &c_vec).is_err());
+
+        assert!(matches!(
+            verify(&maps[..1], &c_vec),
+            Err(ProofVerifyError::LengthMismatch {
+                expected: 5,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn check_pi_staged_chain_proof() {
+        let mut csprng: OsRng = OsRng;
+        let n = 8;
+        let gens = DotProductProofGens::new(n + 5 + 3, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let maps = vec![random_matrix(5, n), random_matrix(3, 5)];
+        let n_matric = random_matrix(2, 3);
+        let z_vec = scalar_math::matrix_vector_mul(
+            &maps[1],
+            &scalar_math::matrix_vector_mul(&maps[0], &x_vec),
+        );
+        let c_vec = scalar_math::matrix_vector_mul(&n_matric, &z_vec);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Staged_Chain_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &maps,
+            &n_matric,
+            &c_vec,
+            &ProveOptions::default(),
+        );
+        let proof = Pi_Staged_Chain_Proof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(proof.commitments().len(), 2);
+
+        let verify = |proof: &Pi_Staged_Chain_Proof, c_vec: &[Scalar]| {
+            let mut verifier_transcript = Transcript::new(b"example");
+            proof.verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &P,
+                &maps,
+                &n_matric,
+                c_vec,
+            )
+        };
+        assert!(verify(&proof, &c_vec).is_ok());
+
+        let mut wrong_c = c_vec.clone();
+        wrong_c[0] += Scalar::one();
+        assert!(verify(&proof, &wrong_c).is_err());
+
+        // the intermediate commitments are bound: swapping them fails
+        let mut swapped = Pi_Staged_Chain_Proof::from_bytes(&proof.to_bytes()).unwrap();
+        swapped.commitments.swap(0, 1);
+        assert!(verify(&swapped, &c_vec).is_err());
+
+        // a single stage, proven and checked on its own: C_1 holds M_1 x at
+        // the generators after those of x
+        let mut prover_transcript = Transcript::new(b"stage");
+        let (stage, y_1, gamma_1, C_1) = Pi_Staged_Chain_Proof::prove_stage(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            0,
+            &x_vec,
+            &gamma,
+            &maps[0],
+            &ProveOptions::default().with_commitment(&P),
+        );
+        assert_eq!(y_1, scalar_math::matrix_vector_mul(&maps[0], &x_vec));
+        assert_eq!(
+            Pi_Affine_Proof::commit(&gens.suffix(n), &y_1, &gamma_1),
+            C_1
+        );
+        let mut verifier_transcript = Transcript::new(b"stage");
+        assert!(Pi_Staged_Chain_Proof::verify_stage(
+            &stage,
+            &gens,
+            &mut verifier_transcript,
+            0,
+            n,
+            &P,
+            &maps[0],
+            &C_1
+        )
+        .is_ok());
+        let mut verifier_transcript = Transcript::new(b"stage");
+        assert!(Pi_Staged_Chain_Proof::verify_stage(
+            &stage,
+            &gens,
+            &mut verifier_transcript,
+            0,
+            n,
+            &P,
+            &maps[0],
+            &proof.commitments()[1]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,420 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::circuit::{Circuit, Gate};
+use crate::commitments::DotProductProofGens;
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::random::RandomTape;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Proof, ProveOptions};
+use crate::zk_protocol::pi_hidden_protocol::{
+    bind_stacked, prove_stacked, stacked_challenges, verify_stacked, Pi_Hidden_Output_Proof,
+};
+use crate::zk_protocol::product_phase::{high_products, ProductEvaluations};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Zero-knowledge proof that the inputs `x` committed in `P` make a
+/// `Circuit` output public values, following Section 5 of the paper.
+///
+/// All other wires are committed right after `x` in `C_rest`, together
+/// with the auxiliary values of the multiplication gates. For the `m` gates
+/// `alpha_i * beta_i = gamma_i`, the prover takes the polynomials `f`, `g` of
+/// degree `m` with `f(i) = alpha_i`, `g(i) = beta_i` and random `f(0)`, `g(0)`,
+/// and commits to `f(0)`, `g(0)` and the evaluations `h(0)`,
+/// `h(m + 1), ..., h(2m)` of `h = f g`; `h(i) = gamma_i` are wires already.
+/// The evaluations `f(c)`, `g(c)`, `h(c)` at a challenge `c` are revealed
+/// through `ProductEvaluations`, and a single Pi_c proof over
+/// `P + e * C_rest` shows them together with every addition and constant
+/// gate and the outputs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Circuit_Proof {
+    C_rest: CompressedGroup,
+    evaluations: ProductEvaluations,
+    proof: Pi_c_Proof,
+}
+
+// Positions of the auxiliary values of the multiplication gates, which
+// follow the wires in the committed vector
+struct AuxLayout {
+    m: usize,
+    f_0: usize,
+    g_0: usize,
+    h_0: usize,
+    // h(m + 1), ..., h(2m)
+    h_high: usize,
+}
+
+impl AuxLayout {
+    fn new(circuit: &Circuit) -> Self {
+        let m = circuit.mul_gates().len();
+        let start = circuit.num_wires();
+        AuxLayout {
+            m,
+            f_0: start,
+            g_0: start + 1,
+            h_0: start + 2,
+            h_high: start + 3,
+        }
+    }
+
+    // the length of the whole committed vector
+    fn len(&self) -> usize {
+        if self.m == 0 {
+            self.f_0
+        } else {
+            self.h_high + self.m
+        }
+    }
+}
+
+impl Pi_Circuit_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_circuit proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        std::mem::size_of::<CompressedGroup>()
+            + 3 * std::mem::size_of::<Scalar>()
+            + self.proof.siz()
+    }
+
+    fn append_statement(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        circuit: &Circuit,
+        aux: &AuxLayout,
+        outputs: &[Scalar],
+        P: &CompressedGroup,
+        C_rest: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        transcript.append_protocol_name(Pi_Circuit_Proof::protocol_name());
+        circuit.append_to_transcript(b"circuit", transcript);
+        outputs
+            .to_vec()
+            .append_to_transcript(b"outputs", transcript);
+        P.append_to_transcript(b"P", transcript);
+        let n = circuit.num_inputs;
+        bind_stacked(gens, transcript, n, aux.len() - n, C_rest)
+    }
+
+    // The positions of f(0..m), g(0..m) and h(0..2m) in the committed vector
+    fn evaluation_positions(
+        circuit: &Circuit,
+        aux: &AuxLayout,
+    ) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+        let gates = circuit.mul_gates();
+        let f = std::iter::once(aux.f_0)
+            .chain(gates.iter().map(|(left, _, _)| *left))
+            .collect();
+        let g = std::iter::once(aux.g_0)
+            .chain(gates.iter().map(|(_, right, _)| *right))
+            .collect();
+        let h = std::iter::once(aux.h_0)
+            .chain(gates.iter().map(|(_, _, out)| *out))
+            .chain(aux.h_high..aux.h_high + aux.m)
+            .collect();
+        (f, g, h)
+    }
+
+    // Every row of the statement as (coefficients by position, value): the
+    // addition and constant gates, the outputs, then the evaluations at c
+    fn statement_rows(
+        circuit: &Circuit,
+        aux: &AuxLayout,
+        outputs: &[Scalar],
+        c: &Scalar,
+        evaluations: &ProductEvaluations,
+    ) -> Vec<(Vec<(usize, Scalar)>, Scalar)> {
+        let one = Scalar::one();
+        let mut rows = Vec::new();
+        for (g, gate) in circuit.gates.iter().enumerate() {
+            let out = circuit.num_inputs + g;
+            match gate {
+                Gate::Add(left, right) => rows.push((
+                    vec![(out, one), (*left, -one), (*right, -one)],
+                    Scalar::zero(),
+                )),
+                Gate::AddConst(input, k) => rows.push((vec![(out, one), (*input, -one)], *k)),
+                Gate::MulConst(input, k) => {
+                    rows.push((vec![(out, one), (*input, -k)], Scalar::zero()))
+                }
+                Gate::Mul(_, _) => {}
+            }
+        }
+        for (wire, value) in circuit.outputs.iter().zip(outputs.iter()) {
+            rows.push((vec![(*wire, one)], *value));
+        }
+
+        if aux.m > 0 {
+            let (f, g, h) = Pi_Circuit_Proof::evaluation_positions(circuit, aux);
+            rows.extend(evaluations.rows(c, &f, &g, &h));
+        }
+        rows
+    }
+
+    // The rho-weighted sum of the rows as a form on (x, e * rest), and its
+    // value
+    fn combined_form(
+        e: &Scalar,
+        rho: &Scalar,
+        num_inputs: usize,
+        len: usize,
+        rows: &[(Vec<(usize, Scalar)>, Scalar)],
+    ) -> (Vec<Scalar>, Scalar) {
+        let rho_vec = scalar_math::vandemonde_challenge_one(*rho, rows.len().max(1));
+        let mut l_vec = vec![Scalar::zero(); len];
+        let mut y = Scalar::zero();
+        for ((coefficients, value), weight) in rows.iter().zip(rho_vec.iter()) {
+            for (position, coefficient) in coefficients {
+                l_vec[*position] += weight * coefficient;
+            }
+            y += weight * value;
+        }
+
+        for l in l_vec[..num_inputs].iter_mut() {
+            *l *= e;
+        }
+        (l_vec, e * y)
+    }
+
+    /// Proves that the inputs `(x, gamma)` opening `P`, which `options` may
+    /// supply, make `circuit` output `circuit.outputs_of(&circuit.evaluate(x))`.
+    /// Returns the proof, `P` and the outputs.
+    pub fn prove(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        circuit: &Circuit,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        options: &ProveOptions,
+    ) -> (Pi_Circuit_Proof, CompressedGroup, Vec<Scalar>) {
+        circuit.check().unwrap();
+        let aux = AuxLayout::new(circuit);
+        let n = circuit.num_inputs;
+        assert!(gens.gens_n.n >= aux.len());
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let wires = circuit.evaluate(x_vec);
+        let outputs = circuit.outputs_of(&wires);
+
+        // the auxiliary values: f(0), g(0), h(0), h(m + 1), ..., h(2m)
+        let mut rest = wires[n..].to_vec();
+        let mut f_vals = Vec::new();
+        let mut g_vals = Vec::new();
+        if aux.m > 0 {
+            f_vals.push(prover_random_tape.random_scalar(b"f_0"));
+            g_vals.push(prover_random_tape.random_scalar(b"g_0"));
+            for (left, right, _) in circuit.mul_gates() {
+                f_vals.push(wires[left]);
+                g_vals.push(wires[right]);
+            }
+            rest.extend_from_slice(&[f_vals[0], g_vals[0], f_vals[0] * g_vals[0]]);
+            rest.extend(high_products(&f_vals, &g_vals));
+        }
+
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let C_rest = Pi_Hidden_Output_Proof::commit_output(gens, n, &rest, &delta);
+        Pi_Circuit_Proof::append_statement(gens, transcript, circuit, &aux, &outputs, &P, &C_rest)
+            .unwrap();
+
+        let (c, evaluations) = ProductEvaluations::prove(transcript, &f_vals, &g_vals);
+
+        let (e, rho) = stacked_challenges(transcript).unwrap();
+        let rows = Pi_Circuit_Proof::statement_rows(circuit, &aux, &outputs, &c, &evaluations);
+        let (l_vec, y) = Pi_Circuit_Proof::combined_form(&e, &rho, n, aux.len(), &rows);
+
+        let proof = prove_stacked(
+            gens,
+            transcript,
+            prover_random_tape,
+            options.mode(),
+            x_vec,
+            gamma,
+            &P,
+            &rest,
+            &delta,
+            &C_rest,
+            &e,
+            &l_vec,
+            &y,
+        );
+
+        let proof = Pi_Circuit_Proof {
+            C_rest,
+            evaluations,
+            proof,
+        };
+        (proof, P, outputs)
+    }
+
+    pub fn verify(
+        &self,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        circuit: &Circuit,
+        P: &CompressedGroup,
+        outputs: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        circuit.check()?;
+        if outputs.len() != circuit.outputs.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: circuit.outputs.len(),
+                found: outputs.len(),
+            });
+        }
+        let aux = AuxLayout::new(circuit);
+        if gens.gens_n.n < aux.len() {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: aux.len(),
+                available: gens.gens_n.n,
+            });
+        }
+
+        Pi_Circuit_Proof::append_statement(
+            gens,
+            transcript,
+            circuit,
+            &aux,
+            outputs,
+            P,
+            &self.C_rest,
+        )?;
+        let c = self.evaluations.verify(transcript)?;
+        let (e, rho) = stacked_challenges(transcript)?;
+
+        let rows = Pi_Circuit_Proof::statement_rows(circuit, &aux, outputs, &c, &self.evaluations);
+        let (l_vec, y) =
+            Pi_Circuit_Proof::combined_form(&e, &rho, circuit.num_inputs, aux.len(), &rows);
+
+        verify_stacked(
+            &self.proof,
+            gens,
+            transcript,
+            P,
+            &self.C_rest,
+            &e,
+            &l_vec,
+            &y,
+        )
+    }
+}
+
+impl ProofEncoding for Pi_Circuit_Proof {
+    const PROOF_TYPE: u8 = 13;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.C_rest);
+        self.evaluations.encode(writer);
+        self.proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Circuit_Proof {
+            C_rest: reader.read_point()?,
+            evaluations: ProductEvaluations::decode(reader)?,
+            proof: Pi_c_Proof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn prove_and_verify(circuit: &Circuit, x_vec: &[Scalar], expected: &[Scalar]) {
+        let mut csprng: OsRng = OsRng;
+        let gens = DotProductProofGens::new(64, b"test-gens");
+        let gamma = Scalar::random(&mut csprng);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P, outputs) = Pi_Circuit_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            circuit,
+            x_vec,
+            &gamma,
+            &ProveOptions::default(),
+        );
+        assert_eq!(outputs, expected);
+        let proof = Pi_Circuit_Proof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(&gens, &mut verifier_transcript, circuit, &P, &outputs)
+            .is_ok());
+
+        for i in 0..outputs.len() {
+            let mut wrong_outputs = outputs.clone();
+            wrong_outputs[i] += Scalar::one();
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(&gens, &mut verifier_transcript, circuit, &P, &wrong_outputs)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn check_pi_circuit_cubic() {
+        // x^3 + x + 5 = 35 for x = 3
+        let mut circuit = Circuit::new(1);
+        let x_2 = circuit.mul(0, 0);
+        let x_3 = circuit.mul(x_2, 0);
+        let sum = circuit.add(x_3, 0);
+        let out = circuit.add_const(sum, Scalar::from(5u64));
+        circuit.output(out);
+
+        prove_and_verify(&circuit, &[Scalar::from(3u64)], &[Scalar::from(35u64)]);
+    }
+
+    #[test]
+    fn check_pi_circuit_inner_product_and_linear() {
+        // <a, b> and 2 a_0 - b_1 for a = (1, 2, 3), b = (4, 5, 6)
+        let mut circuit = Circuit::new(6);
+        let products: Vec<usize> = (0..3).map(|i| circuit.mul(i, 3 + i)).collect();
+        let partial = circuit.add(products[0], products[1]);
+        let dot = circuit.add(partial, products[2]);
+        circuit.output(dot);
+        let double = circuit.mul_const(0, Scalar::from(2u64));
+        let minus_b = circuit.mul_const(4, -Scalar::one());
+        let linear = circuit.add(double, minus_b);
+        circuit.output(linear);
+
+        let x_vec: Vec<Scalar> = (1..=6u64).map(Scalar::from).collect();
+        prove_and_verify(
+            &circuit,
+            &x_vec,
+            &[Scalar::from(32u64), -Scalar::from(3u64)],
+        );
+
+        // a circuit without multiplication gates
+        let mut circuit = Circuit::new(2);
+        let sum = circuit.add(0, 1);
+        circuit.output(sum);
+        prove_and_verify(
+            &circuit,
+            &[Scalar::from(7u64), Scalar::from(8u64)],
+            &[Scalar::from(15u64)],
+        );
+    }
+
+    #[test]
+    fn check_pi_circuit_rejects_malformed_circuits() {
+        let mut circuit = Circuit::new(1);
+        circuit.gates.push(Gate::Mul(0, 1));
+        assert!(circuit.check().is_err());
+
+        let mut circuit = Circuit::new(1);
+        circuit.output(1);
+        assert!(circuit.check().is_err());
+    }
+}
@@ -0,0 +1,578 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::DotProductProofGens;
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
+};
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::curve25519::sparse_matrix::SparseMatrix;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::random::RandomTape;
+use crate::statement_stream::{LinearFormAccumulator, StatementDigest, StatementSink};
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Mode, Pi_c_Proof, ProveOptions};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Proof that `M x = b` where both `x` and the output `b` stay hidden: `x` is
+/// committed in `P` under `G_0, ..., G_{n-1}` and `b` in
+/// `C_b = <b, (G_n, ..., G_{n+s-1})> + delta * h`, see `commit_output`.
+/// With a single row `l`, this proves `<l, x> = y` for a committed `y`.
+///
+/// After `P`, `C_b` and `M` are bound, challenges `e` and `rho` reduce the
+/// statement to one Pi_c proof that `P + e * C_b` opens to `(x, e * b)` with
+/// `<(e * M^T rho, -rho), (x, e * b)> = 0`. Since `e` is random, this only
+/// holds for all `e` if the openings of `P` and `C_b` each satisfy it, so the
+/// prover must know both and not merely one of their sum.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Hidden_Output_Proof {
+    proof: Pi_c_Proof,
+}
+
+impl Pi_Hidden_Output_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_hidden output proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        self.proof.siz()
+    }
+
+    pub fn mode(&self) -> Pi_c_Mode {
+        self.proof.mode()
+    }
+
+    /// The commitment `C_b` to an output vector of a statement over a vector
+    /// of length `n`: `b` sits right after `x`, under `G_n, ..., G_{n+s-1}`
+    pub fn commit_output(
+        gens: &DotProductProofGens,
+        n: usize,
+        b_vec: &[Scalar],
+        delta: &Scalar,
+    ) -> CompressedGroup {
+        assert!(gens.gens_n.n >= n + b_vec.len());
+        GroupElement::vartime_multiscalar_mul(
+            b_vec.iter().chain(std::iter::once(delta)),
+            gens.gens_n.G[n..n + b_vec.len()]
+                .iter()
+                .chain(std::iter::once(&gens.gens_n.h)),
+        )
+        .compress()
+    }
+
+    fn statement_challenges(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        digest: &[u8; 32],
+        n: usize,
+        s: usize,
+        C_b: &CompressedGroup,
+    ) -> Result<(Scalar, Scalar), ProofVerifyError> {
+        transcript.append_protocol_name(Pi_Hidden_Output_Proof::protocol_name());
+        transcript.append_message(b"statement_digest", digest);
+        bind_stacked(gens, transcript, n, s, C_b)?;
+        stacked_challenges(transcript)
+    }
+
+    // The linear form (e * M^T rho, -rho) on (x, e * b)
+    fn combined_form(e: &Scalar, rho: &Scalar, m_t_rho: &[Scalar], s: usize) -> Vec<Scalar> {
+        let mut l_vec = scalar_math::scalar_vector_mul(e, m_t_rho);
+        l_vec.extend(
+            scalar_math::vandemonde_challenge_one(*rho, s)
+                .iter()
+                .map(|r| -r),
+        );
+        l_vec
+    }
+
+    /// Proves that `C_b` commits to `M x` for the opening `(x, gamma)` of `P`,
+    /// which `options` may supply. Returns the proof, `P` and `C_b` with a
+    /// fresh blinding for `C_b`.
+    pub fn prove(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        m_matric: &[Vec<Scalar>],
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup, CompressedGroup) {
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let b_vec = scalar_math::matrix_vector_mul(m_matric, x_vec);
+        let C_b = Pi_Hidden_Output_Proof::commit_output(gens, x_vec.len(), &b_vec, &delta);
+        let (proof, P) = Pi_Hidden_Output_Proof::prove_with_output(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &delta,
+            &C_b,
+            m_matric,
+            options,
+        );
+        (proof, P, C_b)
+    }
+
+    /// Same as `prove`, for an output commitment the caller already holds:
+    /// `C_b` must be `commit_output(gens, n, M x, delta)`
+    pub fn prove_with_output(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        delta: &Scalar,
+        C_b: &CompressedGroup,
+        m_matric: &[Vec<Scalar>],
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup) {
+        let n = x_vec.len();
+        assert!(m_matric.iter().all(|row| row.len() == n));
+        let b_vec = scalar_math::matrix_vector_mul(m_matric, x_vec);
+
+        Pi_Hidden_Output_Proof::prove_core(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &b_vec,
+            delta,
+            C_b,
+            |sink| {
+                for row in m_matric {
+                    sink.add_row(row);
+                }
+            },
+            options,
+        )
+    }
+
+    pub fn prove_sparse(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        m_matric: &SparseMatrix,
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup, CompressedGroup) {
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let b_vec = m_matric.vector_mul(x_vec);
+        let C_b = Pi_Hidden_Output_Proof::commit_output(gens, x_vec.len(), &b_vec, &delta);
+        let (proof, P) = Pi_Hidden_Output_Proof::prove_sparse_with_output(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &delta,
+            &C_b,
+            m_matric,
+            options,
+        );
+        (proof, P, C_b)
+    }
+
+    pub fn prove_sparse_with_output(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        delta: &Scalar,
+        C_b: &CompressedGroup,
+        m_matric: &SparseMatrix,
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup) {
+        assert_eq!(m_matric.num_cols, x_vec.len());
+        assert!(m_matric.is_well_formed());
+        let b_vec = m_matric.vector_mul(x_vec);
+
+        Pi_Hidden_Output_Proof::prove_core(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &b_vec,
+            delta,
+            C_b,
+            |sink| {
+                sink.add_sparse(m_matric);
+            },
+            options,
+        )
+    }
+
+    fn prove_core(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        b_vec: &[Scalar],
+        delta: &Scalar,
+        C_b: &CompressedGroup,
+        mut read_rows: impl FnMut(&mut dyn StatementSink),
+        options: &ProveOptions,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup) {
+        let (n, s) = (x_vec.len(), b_vec.len());
+        assert!(s >= 1);
+        assert!(gens.gens_n.n >= n + s);
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        // M only: the b column of the digest stays zero, b is bound via C_b
+        let mut digest = StatementDigest::new(n + 1, &gens.fingerprint(0..n).unwrap(), &P);
+        read_rows(&mut digest);
+        assert_eq!(digest.num_rows(), s);
+        let (e, rho) = Pi_Hidden_Output_Proof::statement_challenges(
+            gens,
+            transcript,
+            &digest.finalize(),
+            n,
+            s,
+            C_b,
+        )
+        .unwrap();
+
+        let mut acc = LinearFormAccumulator::new(n, rho);
+        read_rows(&mut acc);
+        assert_eq!(acc.num_rows(), s, "the statement changed between passes");
+        let l_vec = Pi_Hidden_Output_Proof::combined_form(&e, &rho, &acc.finalize(), s);
+
+        let proof = prove_stacked(
+            gens,
+            transcript,
+            prover_random_tape,
+            options.mode(),
+            x_vec,
+            gamma,
+            &P,
+            b_vec,
+            delta,
+            C_b,
+            &e,
+            &l_vec,
+            &Scalar::zero(),
+        );
+
+        (Pi_Hidden_Output_Proof { proof }, P)
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        m_matric: &[Vec<Scalar>],
+        P: &CompressedGroup,
+        C_b: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if let Some(row) = m_matric.iter().find(|row| row.len() != n) {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: row.len(),
+            });
+        }
+
+        self.verify_core(n, gens, transcript, P, C_b, |sink| {
+            for row in m_matric {
+                sink.add_row(row);
+            }
+        })
+    }
+
+    pub fn verify_sparse(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        m_matric: &SparseMatrix,
+        P: &CompressedGroup,
+        C_b: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if m_matric.num_cols != n {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: n,
+                found: m_matric.num_cols,
+            });
+        }
+        if !m_matric.is_well_formed() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "sparse matrix entry out of bounds".to_string(),
+            ));
+        }
+
+        self.verify_core(n, gens, transcript, P, C_b, |sink| {
+            sink.add_sparse(m_matric)
+        })
+    }
+
+    fn verify_core(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        C_b: &CompressedGroup,
+        mut read_rows: impl FnMut(&mut dyn StatementSink),
+    ) -> Result<(), ProofVerifyError> {
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "statement has no columns".to_string(),
+            ));
+        }
+
+        let mut digest = StatementDigest::new(n + 1, &gens.fingerprint(0..n)?, P);
+        read_rows(&mut digest);
+        let s = digest.num_rows();
+        if s == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "matrix has no rows".to_string(),
+            ));
+        }
+        if gens.gens_n.n < n + s {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n + s,
+                available: gens.gens_n.n,
+            });
+        }
+
+        let (e, rho) = Pi_Hidden_Output_Proof::statement_challenges(
+            gens,
+            transcript,
+            &digest.finalize(),
+            n,
+            s,
+            C_b,
+        )?;
+
+        let mut acc = LinearFormAccumulator::new(n, rho);
+        read_rows(&mut acc);
+        let l_vec = Pi_Hidden_Output_Proof::combined_form(&e, &rho, &acc.finalize(), s);
+
+        verify_stacked(
+            &self.proof,
+            gens,
+            transcript,
+            P,
+            C_b,
+            &e,
+            &l_vec,
+            &Scalar::zero(),
+        )
+    }
+}
+
+// The helpers below stack a vector `rest`, committed in `C_rest` right after
+// `x` (see `Pi_Hidden_Output_Proof::commit_output`), onto `x`: a statement
+// over `(x, rest)` becomes one Pi_c proof that `P + e * C_rest` opens to
+// `(x, e * rest)`. `Pi_Chain_Proof`, `Pi_Range_Proof`, `Pi_Circuit_Proof` and
+// `Pi_Quadratic_Proof` are built on them the same way as the proof above:
+// bind the statement, `bind_stacked`, any further rounds, then
+// `stacked_challenges` and `prove_stacked`/`verify_stacked` over the form.
+
+/// Binds `C_rest`, a commitment to `m` values placed after a vector of
+/// length `n`, and the generators `G_n, ..., G_{n+m-1}` it is taken under
+pub(crate) fn bind_stacked(
+    gens: &DotProductProofGens,
+    transcript: &mut Transcript,
+    n: usize,
+    m: usize,
+    C_rest: &CompressedGroup,
+) -> Result<(), ProofVerifyError> {
+    C_rest.append_to_transcript(b"C_rest", transcript);
+    transcript.append_message(b"rest_gens", &gens.fingerprint(n..n + m)?);
+    Ok(())
+}
+
+/// The challenge `e` weighing `rest` against `x`, and `rho` for combining the
+/// rows of the statement. `e` must not be zero, or `P + e * C_rest` would not
+/// depend on `rest` at all.
+pub(crate) fn stacked_challenges(
+    transcript: &mut Transcript,
+) -> Result<(Scalar, Scalar), ProofVerifyError> {
+    let e = transcript.challenge_scalar(b"e");
+    let rho = transcript.challenge_scalar(b"rho");
+    if e == Scalar::zero() {
+        return Err(ProofVerifyError::ZeroChallenge);
+    }
+    Ok((e, rho))
+}
+
+/// Proves `<l, (x, e * rest)> = y` for the opening
+/// `((x, e * rest), gamma + e * delta)` of `P + e * C_rest`
+pub(crate) fn prove_stacked(
+    gens: &DotProductProofGens,
+    transcript: &mut Transcript,
+    prover_random_tape: &mut RandomTape,
+    mode: Pi_c_Mode,
+    x_vec: &[Scalar],
+    gamma: &Scalar,
+    P: &CompressedGroup,
+    rest: &[Scalar],
+    delta: &Scalar,
+    C_rest: &CompressedGroup,
+    e: &Scalar,
+    l_vec: &[Scalar],
+    y: &Scalar,
+) -> Pi_c_Proof {
+    let mut w_hat = x_vec.to_vec();
+    w_hat.extend(rest.iter().map(|r| e * r));
+    let gamma_hat = gamma + e * delta;
+    let P_hat = (P.unpack().unwrap() + e * C_rest.unpack().unwrap()).compress();
+
+    let (proof, _) = Pi_c_Proof::prove(
+        gens,
+        transcript,
+        prover_random_tape,
+        &w_hat,
+        &gamma_hat,
+        l_vec,
+        y,
+        &ProveOptions::default()
+            .with_mode(mode)
+            .with_commitment(&P_hat),
+    );
+    proof
+}
+
+/// Checks a `prove_stacked` proof of `<l, (x, e * rest)> = y`; the length
+/// of the stacked vector is that of `l`
+pub(crate) fn verify_stacked(
+    proof: &Pi_c_Proof,
+    gens: &DotProductProofGens,
+    transcript: &mut Transcript,
+    P: &CompressedGroup,
+    C_rest: &CompressedGroup,
+    e: &Scalar,
+    l_vec: &[Scalar],
+    y: &Scalar,
+) -> Result<(), ProofVerifyError> {
+    let P_hat = (P.unpack()? + e * C_rest.unpack()?).compress();
+    proof.verify(l_vec.len(), gens, transcript, l_vec, &P_hat, y)
+}
+
+impl ProofEncoding for Pi_Hidden_Output_Proof {
+    const PROOF_TYPE: u8 = 8;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        self.proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Hidden_Output_Proof {
+            proof: Pi_c_Proof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_hidden_output_proof() {
+        let mut csprng: OsRng = OsRng;
+        let (n, s) = (9, 3);
+        let gens = DotProductProofGens::new(n + s, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let gamma = Scalar::random(&mut csprng);
+        let m_matric: Vec<Vec<Scalar>> = (0..s)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect();
+        let sparse = SparseMatrix::from_dense(&m_matric);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P, C_b) = Pi_Hidden_Output_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &m_matric,
+            &ProveOptions::default(),
+        );
+        let proof = Pi_Hidden_Output_Proof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &m_matric, &P, &C_b)
+            .is_ok());
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_sparse(n, &gens, &mut verifier_transcript, &sparse, &P, &C_b)
+            .is_ok());
+
+        // a commitment to a different output is rejected
+        let mut b_vec = scalar_math::matrix_vector_mul(&m_matric, &x_vec);
+        b_vec[1] += Scalar::one();
+        let delta = Scalar::random(&mut csprng);
+        let wrong_C_b = Pi_Hidden_Output_Proof::commit_output(&gens, n, &b_vec, &delta);
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &m_matric,
+                &P,
+                &wrong_C_b
+            )
+            .is_err());
+
+        // and so is a false statement proven against it
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, _) = Pi_Hidden_Output_Proof::prove_core(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &b_vec,
+            &delta,
+            &wrong_C_b,
+            |sink| {
+                sink.add_sparse(&sparse);
+            },
+            &ProveOptions::default().with_commitment(&P),
+        );
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &m_matric,
+                &P,
+                &wrong_C_b
+            ),
+            Err(ProofVerifyError::FinalCheckFailed)
+        ));
+
+        assert!(matches!(
+            proof.verify(
+                n,
+                &DotProductProofGens::new(n + s - 1, b"test-gens"),
+                &mut Transcript::new(b"example"),
+                &m_matric,
+                &P,
+                &C_b
+            ),
+            Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: 12,
+                available: 11
+            })
+        ));
+    }
+}
@@ -0,0 +1,521 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::{Commitments, InnerProductProofGens};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
+};
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math::inner_product;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::nozk_protocol::bullet_proof::InnerProductReductionProof;
+use crate::random::RandomTape;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use curve25519_dalek::traits::Identity;
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Zero-knowledge proof of `<a, b> = c` for `a` committed as
+/// `P_a = <a, G> + alpha_a * h` and `b` as `P_b = <b, H> + alpha_b * h`, with
+/// `c` either public or committed as `V = c * k + gamma * h`.
+///
+/// Once `P_a` and `P_b` are bound, a challenge `u` weighs `b` against `a`:
+/// everything below runs over `P_a + u * P_b`, with `b` committed under
+/// `H' = u * H`, so that the prover has to know openings of `P_a` and `P_b`
+/// separately and not only of their sum.
+///
+/// The prover blinds both vectors with random `s_a`, `s_b` committed in `S`,
+/// so that `l(X) = a + s_a X` and `r(X) = b + s_b X` have
+/// `<l(X), r(X)> = c + t_1 X + t_2 X^2`. After committing to `t_1` and `t_2`
+/// in `T_1` and `T_2` and receiving `x`, it opens `t_hat = <l(x), r(x)>`
+/// against `V + x T_1 + x^2 T_2` and proves knowledge of `l(x)` and `r(x)`
+/// with the two-vector folding argument, which may reveal them since they
+/// are masked by `s_a` and `s_b`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_IPA_Proof {
+    S: CompressedGroup,
+    T_1: CompressedGroup,
+    T_2: CompressedGroup,
+    tau_x: Scalar,
+    mu: Scalar,
+    t_hat: Scalar,
+    ipa_proof: InnerProductReductionProof,
+}
+
+// The value of <a, b> as the verifier knows it
+enum Claim<'a> {
+    Public(&'a Scalar),
+    Committed(&'a CompressedGroup),
+}
+
+impl Pi_IPA_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_ipa proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        3 * std::mem::size_of::<CompressedGroup>()
+            + 3 * std::mem::size_of::<Scalar>()
+            + self.ipa_proof.siz()
+    }
+
+    fn append_statement(
+        transcript: &mut Transcript,
+        n: usize,
+        P_a: &CompressedGroup,
+        P_b: &CompressedGroup,
+        claim: &Claim,
+    ) {
+        transcript.append_protocol_name(Pi_IPA_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        P_a.append_to_transcript(b"P_a", transcript);
+        P_b.append_to_transcript(b"P_b", transcript);
+        match claim {
+            Claim::Public(c) => c.append_to_transcript(b"c", transcript),
+            Claim::Committed(V) => V.append_to_transcript(b"V", transcript),
+        }
+    }
+
+    /// Proves `<a, b> = c` for the public `c`, which the verifier computes
+    /// or receives alongside the commitments
+    pub fn prove(
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        a_vec: &[Scalar],
+        alpha_a: &Scalar,
+        b_vec: &[Scalar],
+        alpha_b: &Scalar,
+    ) -> (Pi_IPA_Proof, CompressedGroup, CompressedGroup, Scalar) {
+        let gens = gens.prefix(a_vec.len());
+        let P_a = a_vec.commit(alpha_a, &gens.gens_a).compress();
+        let P_b = b_vec.commit(alpha_b, &gens.gens_b).compress();
+        let c = inner_product(a_vec, b_vec);
+        let proof = Pi_IPA_Proof::prove_core(
+            &gens,
+            transcript,
+            prover_random_tape,
+            a_vec,
+            alpha_a,
+            b_vec,
+            alpha_b,
+            &P_a,
+            &P_b,
+            &Claim::Public(&c),
+            &Scalar::zero(),
+        );
+        (proof, P_a, P_b, c)
+    }
+
+    /// Proves `<a, b> = c` for `c` committed as `V = c * k + gamma * h`
+    pub fn prove_committed(
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        a_vec: &[Scalar],
+        alpha_a: &Scalar,
+        b_vec: &[Scalar],
+        alpha_b: &Scalar,
+        gamma: &Scalar,
+    ) -> (
+        Pi_IPA_Proof,
+        CompressedGroup,
+        CompressedGroup,
+        CompressedGroup,
+    ) {
+        let gens = gens.prefix(a_vec.len());
+        let P_a = a_vec.commit(alpha_a, &gens.gens_a).compress();
+        let P_b = b_vec.commit(alpha_b, &gens.gens_b).compress();
+        let V = inner_product(a_vec, b_vec)
+            .commit(gamma, &gens.gens_1)
+            .compress();
+        let proof = Pi_IPA_Proof::prove_core(
+            &gens,
+            transcript,
+            prover_random_tape,
+            a_vec,
+            alpha_a,
+            b_vec,
+            alpha_b,
+            &P_a,
+            &P_b,
+            &Claim::Committed(&V),
+            gamma,
+        );
+        (proof, P_a, P_b, V)
+    }
+
+    // `gens` is already cut to n = a_vec.len(); `gamma` is zero for a public c
+    fn prove_core(
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        a_vec: &[Scalar],
+        alpha_a: &Scalar,
+        b_vec: &[Scalar],
+        alpha_b: &Scalar,
+        P_a: &CompressedGroup,
+        P_b: &CompressedGroup,
+        claim: &Claim,
+        gamma: &Scalar,
+    ) -> Pi_IPA_Proof {
+        let n = a_vec.len();
+        assert!(n >= 1);
+        assert_eq!(b_vec.len(), n);
+        assert_eq!(gens.gens_a.n, n);
+
+        Pi_IPA_Proof::append_statement(transcript, n, P_a, P_b, claim);
+        let u = transcript.challenge_scalar(b"u");
+
+        // blinding vectors for a and b, with s_b under H' = u * H
+        let s_a = prover_random_tape.random_vector(b"s_a", n);
+        let s_b = prover_random_tape.random_vector(b"s_b", n);
+        let rho = prover_random_tape.random_scalar(b"rho");
+        let S = (s_a.commit(&rho, &gens.gens_a) + u * s_b.commit(&Scalar::zero(), &gens.gens_b))
+            .compress();
+        S.append_to_transcript(b"S", transcript);
+
+        // <a + s_a X, b + s_b X> = <a, b> + t_1 X + t_2 X^2
+        let t_1 = inner_product(a_vec, &s_b) + inner_product(&s_a, b_vec);
+        let t_2 = inner_product(&s_a, &s_b);
+        let tau_1 = prover_random_tape.random_scalar(b"tau_1");
+        let tau_2 = prover_random_tape.random_scalar(b"tau_2");
+        let T_1 = t_1.commit(&tau_1, &gens.gens_1).compress();
+        let T_2 = t_2.commit(&tau_2, &gens.gens_1).compress();
+        T_1.append_to_transcript(b"T_1", transcript);
+        T_2.append_to_transcript(b"T_2", transcript);
+
+        let x = transcript.challenge_scalar(b"x");
+        let l_vec: Vec<Scalar> = a_vec.iter().zip(&s_a).map(|(a, s)| a + x * s).collect();
+        let r_vec: Vec<Scalar> = b_vec.iter().zip(&s_b).map(|(b, s)| b + x * s).collect();
+        let t_hat = inner_product(&l_vec, &r_vec);
+        let tau_x = tau_2 * x * x + tau_1 * x + gamma;
+        let mu = alpha_a + u * alpha_b + rho * x;
+
+        tau_x.append_to_transcript(b"tau_x", transcript);
+        mu.append_to_transcript(b"mu", transcript);
+        t_hat.append_to_transcript(b"t_hat", transcript);
+
+        // a fresh multiple of k carries the inner product in the folding, so
+        // the prover cannot shift value between it and the vectors
+        let w = transcript.challenge_scalar(b"w");
+        let Q = w * gens.gens_1.G[0];
+        let H_prime: Vec<GroupElement> = gens.gens_b.G.iter().map(|H| u * H).collect();
+        let ipa_proof = InnerProductReductionProof::prove(
+            transcript,
+            &Q,
+            &gens.gens_a.G,
+            &H_prime,
+            &l_vec,
+            &r_vec,
+        );
+
+        Pi_IPA_Proof {
+            S,
+            T_1,
+            T_2,
+            tau_x,
+            mu,
+            t_hat,
+            ipa_proof,
+        }
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        P_a: &CompressedGroup,
+        P_b: &CompressedGroup,
+        c: &Scalar,
+    ) -> Result<(), ProofVerifyError> {
+        self.verify_core(n, gens, transcript, P_a, P_b, &Claim::Public(c))
+    }
+
+    pub fn verify_committed(
+        &self,
+        n: usize,
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        P_a: &CompressedGroup,
+        P_b: &CompressedGroup,
+        V: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        self.verify_core(n, gens, transcript, P_a, P_b, &Claim::Committed(V))
+    }
+
+    fn verify_core(
+        &self,
+        n: usize,
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        P_a: &CompressedGroup,
+        P_b: &CompressedGroup,
+        claim: &Claim,
+    ) -> Result<(), ProofVerifyError> {
+        let available = gens.gens_a.n.min(gens.gens_b.n);
+        if available < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available,
+            });
+        }
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "inner product of empty vectors".to_string(),
+            ));
+        }
+
+        Pi_IPA_Proof::append_statement(transcript, n, P_a, P_b, claim);
+        let u = transcript.challenge_scalar(b"u");
+        self.S.append_to_transcript(b"S", transcript);
+        self.T_1.append_to_transcript(b"T_1", transcript);
+        self.T_2.append_to_transcript(b"T_2", transcript);
+        let x = transcript.challenge_scalar(b"x");
+        self.tau_x.append_to_transcript(b"tau_x", transcript);
+        self.mu.append_to_transcript(b"mu", transcript);
+        self.t_hat.append_to_transcript(b"t_hat", transcript);
+        let w = transcript.challenge_scalar(b"w");
+
+        let k = gens.gens_1.G[0];
+        let h = gens.gens_1.h;
+
+        // t_hat * k + tau_x * h == V + x * T_1 + x^2 * T_2
+        let V = match claim {
+            Claim::Public(c) => *c * k,
+            Claim::Committed(V) => V.unpack()?,
+        };
+        let value_check = GroupElement::vartime_multiscalar_mul(
+            &[self.t_hat, self.tau_x, -Scalar::one(), -x, -(x * x)],
+            &[k, h, V, self.T_1.unpack()?, self.T_2.unpack()?],
+        );
+        if value_check != GroupElement::identity() {
+            return Err(ProofVerifyError::FinalCheckFailed);
+        }
+
+        // P_a + u * P_b + x * S - mu * h = <l(x), G> + <r(x), u * H> joins the
+        // folded relation together with t_hat * w * k
+        let (G_scalars, H_scalars, Q_scalar, mut scalars, mut points) =
+            self.ipa_proof.verification_terms(n, transcript)?;
+        let H_scalars: Vec<Scalar> = H_scalars.iter().map(|s| s * u).collect();
+        scalars.extend_from_slice(&[Scalar::one(), u, x, -self.mu, (self.t_hat + Q_scalar) * w]);
+        points.extend_from_slice(&[P_a.unpack()?, P_b.unpack()?, self.S.unpack()?, h, k]);
+
+        let result = GroupElement::vartime_multiscalar_mul(
+            G_scalars
+                .iter()
+                .chain(H_scalars.iter())
+                .chain(scalars.iter()),
+            gens.gens_a.G[..n]
+                .iter()
+                .chain(gens.gens_b.G[..n].iter())
+                .chain(points.iter()),
+        );
+
+        if result == GroupElement::identity() {
+            Ok(())
+        } else {
+            Err(ProofVerifyError::FinalCheckFailed)
+        }
+    }
+}
+
+impl ProofEncoding for Pi_IPA_Proof {
+    const PROOF_TYPE: u8 = 11;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.S);
+        writer.write_point(&self.T_1);
+        writer.write_point(&self.T_2);
+        writer.write_scalar(&self.tau_x);
+        writer.write_scalar(&self.mu);
+        writer.write_scalar(&self.t_hat);
+        self.ipa_proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_IPA_Proof {
+            S: reader.read_point()?,
+            T_1: reader.read_point()?,
+            T_2: reader.read_point()?,
+            tau_x: reader.read_scalar()?,
+            mu: reader.read_scalar()?,
+            t_hat: reader.read_scalar()?,
+            ipa_proof: InnerProductReductionProof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_ipa_proof() {
+        let mut csprng: OsRng = OsRng;
+        let gens = InnerProductProofGens::new(9, b"test-gens");
+
+        for n in [1, 2, 5, 8, 9] {
+            let a_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let b_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let alpha_a = Scalar::random(&mut csprng);
+            let alpha_b = Scalar::random(&mut csprng);
+
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, P_a, P_b, c) = Pi_IPA_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &a_vec,
+                &alpha_a,
+                &b_vec,
+                &alpha_b,
+            );
+            let proof = Pi_IPA_Proof::from_bytes(&proof.to_bytes()).unwrap();
+            let lg = InnerProductReductionProof::padded_len(n).trailing_zeros() as usize;
+            assert_eq!(
+                proof.to_bytes().len(),
+                6 + 3 * 32 + 3 * 32 + 2 * (4 + lg * 32) + 2 * 32
+            );
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n, &gens, &mut verifier_transcript, &P_a, &P_b, &c)
+                .is_ok());
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(matches!(
+                proof.verify(
+                    n,
+                    &gens,
+                    &mut verifier_transcript,
+                    &P_a,
+                    &P_b,
+                    &(c + Scalar::one())
+                ),
+                Err(ProofVerifyError::FinalCheckFailed)
+            ));
+
+            // the commitments are not interchangeable
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof
+                .verify(n, &gens, &mut verifier_transcript, &P_b, &P_a, &c)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn check_pi_ipa_proof_binds_each_commitment() {
+        let mut csprng: OsRng = OsRng;
+        let n = 5;
+        let gens = InnerProductProofGens::new(n, b"test-gens");
+        let a_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let b_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let (alpha_a, alpha_b) = (Scalar::random(&mut csprng), Scalar::random(&mut csprng));
+        let c = inner_product(&a_vec, &b_vec);
+
+        // (P_a + D, P_b - D) has the same sum as the honest pair, but neither
+        // commitment opens to the vector the proof is about
+        let D = Scalar::random(&mut csprng) * gens.gens_1.G[0];
+        let P_a = (a_vec.commit(&alpha_a, &gens.gens_a) + D).compress();
+        let P_b = (b_vec.commit(&alpha_b, &gens.gens_b) - D).compress();
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let proof = Pi_IPA_Proof::prove_core(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &a_vec,
+            &alpha_a,
+            &b_vec,
+            &alpha_b,
+            &P_a,
+            &P_b,
+            &Claim::Public(&c),
+            &Scalar::zero(),
+        );
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(n, &gens, &mut verifier_transcript, &P_a, &P_b, &c),
+            Err(ProofVerifyError::FinalCheckFailed)
+        ));
+    }
+
+    #[test]
+    fn check_pi_ipa_proof_rejects_short_key() {
+        let n = 4;
+        let gens = InnerProductProofGens::new(n, b"test-gens");
+        let v_vec = vec![Scalar::one(); n];
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P_a, P_b, c) = Pi_IPA_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &v_vec,
+            &Scalar::one(),
+            &v_vec,
+            &Scalar::one(),
+        );
+
+        // a key with fewer H than G is reported, not sliced out of bounds
+        let mut short = InnerProductProofGens::new(n, b"test-gens");
+        short.gens_b = short.gens_b.prefix(n - 1);
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(n, &short, &mut verifier_transcript, &P_a, &P_b, &c),
+            Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: 4,
+                available: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn check_pi_ipa_proof_with_committed_value() {
+        let mut csprng: OsRng = OsRng;
+        let n = 6;
+        let gens = InnerProductProofGens::new(n, b"test-gens");
+        let a_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let b_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let (alpha_a, alpha_b, gamma) = (
+            Scalar::random(&mut csprng),
+            Scalar::random(&mut csprng),
+            Scalar::random(&mut csprng),
+        );
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P_a, P_b, V) = Pi_IPA_Proof::prove_committed(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &a_vec,
+            &alpha_a,
+            &b_vec,
+            &alpha_b,
+            &gamma,
+        );
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify_committed(n, &gens, &mut verifier_transcript, &P_a, &P_b, &V)
+            .is_ok());
+
+        let wrong_V = (inner_product(&a_vec, &b_vec) + Scalar::one())
+            .commit(&gamma, &gens.gens_1)
+            .compress();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify_committed(n, &gens, &mut verifier_transcript, &P_a, &P_b, &wrong_V),
+            Err(ProofVerifyError::FinalCheckFailed)
+        ));
+    }
+}
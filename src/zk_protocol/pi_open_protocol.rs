@@ -0,0 +1,224 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+use crate::commitments::{Commitments, MultiCommitGens};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
+};
+use crate::curve25519::scalar::Scalar;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::nozk_protocol::bullet_proof::BulletReductionProof;
+use crate::random::RandomTape;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::sigma_phase;
+use curve25519_dalek::traits::Identity;
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Compressed proof of knowledge of an opening `(x, gamma)` of
+/// `P = <x, G> + gamma * h`, with no statement about `x`. The sigma protocol
+/// gives `A = <r, G> + rho * h` and the response `(z, phi)` with
+/// `<z, G> + phi * h = A + c * P`; instead of sending the response, its
+/// knowledge is proven by folding over `(G, h)` with an all-zero linear form,
+/// so the proof holds `2 ceil(log2(n + 1)) - 1` points and 2 scalars.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Open_Proof {
+    A: CompressedGroup,
+    bullet_reduction_proof: BulletReductionProof,
+}
+
+impl Pi_Open_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_open proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        std::mem::size_of::<CompressedGroup>() + self.bullet_reduction_proof.siz()
+    }
+
+    pub fn prove(
+        gens: &MultiCommitGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+    ) -> (Pi_Open_Proof, CompressedGroup) {
+        assert!(gens.n >= x_vec.len());
+        let P = x_vec.commit(gamma, &gens.prefix(x_vec.len())).compress();
+        let proof = Pi_Open_Proof::prove_with_commitment(
+            gens,
+            transcript,
+            prover_random_tape,
+            x_vec,
+            gamma,
+            &P,
+        );
+        (proof, P)
+    }
+
+    // Proves knowledge of the opening (x, gamma) of an existing commitment P
+    // under the first n generators of `gens`; panics if P does not open to it
+    pub fn prove_with_commitment(
+        gens: &MultiCommitGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        P: &CompressedGroup,
+    ) -> Pi_Open_Proof {
+        let n = x_vec.len();
+        assert!(n >= 1);
+        assert!(gens.n >= n);
+        let gens_n = gens.prefix(n);
+        assert_eq!(
+            x_vec.commit(gamma, &gens_n).compress(),
+            *P,
+            "P is not a commitment to (x, gamma)"
+        );
+
+        transcript.append_protocol_name(Pi_Open_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        P.append_to_transcript(b"P", transcript);
+
+        let (A, r_vec, rho) =
+            sigma_phase::opening_commit_phase(transcript, prover_random_tape, &gens_n);
+        let c = sigma_phase::challenge_phase(transcript);
+        let (mut z_hat, phi) = sigma_phase::response_phase(&c, x_vec, &r_vec, gamma, &rho);
+        z_hat.push(phi);
+
+        let mut G_hat = gens_n.G;
+        G_hat.push(gens_n.h);
+
+        // with a zero linear form the k terms of the folding vanish, so no k
+        // generator is needed
+        let zeros = vec![Scalar::zero(); n + 1];
+        let bullet_reduction_proof = BulletReductionProof::prove(
+            transcript,
+            &GroupElement::identity(),
+            &G_hat,
+            &z_hat,
+            &zeros,
+        );
+
+        Pi_Open_Proof {
+            A,
+            bullet_reduction_proof,
+        }
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &MultiCommitGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        if gens.n < n {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n,
+                available: gens.n,
+            });
+        }
+        if n == 0 {
+            return Err(ProofVerifyError::MalformedStatement(
+                "commitment to an empty vector".to_string(),
+            ));
+        }
+
+        transcript.append_protocol_name(Pi_Open_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        P.append_to_transcript(b"P", transcript);
+        self.A.append_to_transcript(b"A", transcript);
+        let c = sigma_phase::challenge_phase(transcript);
+
+        let zeros = vec![Scalar::zero(); n + 1];
+        let (G_scalars, _, mut scalars, mut points) = self
+            .bullet_reduction_proof
+            .verification_terms(n + 1, &zeros, transcript)?;
+
+        // Q = A + c * P joins the folded relation over (G, h)
+        scalars.extend_from_slice(&[Scalar::one(), c]);
+        points.extend_from_slice(&[self.A.unpack()?, P.unpack()?]);
+
+        let result = GroupElement::vartime_multiscalar_mul(
+            G_scalars.iter().chain(scalars.iter()),
+            gens.G[..n]
+                .iter()
+                .chain(std::iter::once(&gens.h))
+                .chain(points.iter()),
+        );
+
+        if result == GroupElement::identity() {
+            Ok(())
+        } else {
+            Err(ProofVerifyError::FinalCheckFailed)
+        }
+    }
+}
+
+impl ProofEncoding for Pi_Open_Proof {
+    const PROOF_TYPE: u8 = 7;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.A);
+        self.bullet_reduction_proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Open_Proof {
+            A: reader.read_point()?,
+            bullet_reduction_proof: BulletReductionProof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_open_proof() {
+        let mut csprng: OsRng = OsRng;
+        let gens = MultiCommitGens::new(20, b"test-gens");
+
+        for n in [1, 2, 3, 7, 8, 20] {
+            let x_vec: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+            let gamma = Scalar::random(&mut csprng);
+
+            let mut prover_random_tape = RandomTape::new(b"proof");
+            let mut prover_transcript = Transcript::new(b"example");
+            let (proof, P) = Pi_Open_Proof::prove(
+                &gens,
+                &mut prover_transcript,
+                &mut prover_random_tape,
+                &x_vec,
+                &gamma,
+            );
+
+            let proof = Pi_Open_Proof::from_bytes(&proof.to_bytes()).unwrap();
+            let lg = BulletReductionProof::padded_len(n + 1).trailing_zeros() as usize;
+            assert_eq!(
+                proof.to_bytes().len(),
+                6 + 2 * 4 + (2 * lg - 1) * 32 + 4 + 2 * 32
+            );
+
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(proof.verify(n, &gens, &mut verifier_transcript, &P).is_ok());
+
+            // a different commitment, or the same one read as a shorter vector
+            let other_P = (x_vec.commit(&(gamma + Scalar::one()), &gens.prefix(n))).compress();
+            let mut verifier_transcript = Transcript::new(b"example");
+            assert!(matches!(
+                proof.verify(n, &gens, &mut verifier_transcript, &other_P),
+                Err(ProofVerifyError::FinalCheckFailed)
+            ));
+            if n > 1 {
+                let mut verifier_transcript = Transcript::new(b"example");
+                assert!(proof
+                    .verify(n - 1, &gens, &mut verifier_transcript, &P)
+                    .is_err());
+            }
+        }
+    }
+}
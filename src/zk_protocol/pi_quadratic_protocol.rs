@@ -0,0 +1,388 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::DotProductProofGens;
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::random::RandomTape;
+use crate::statement_stream::{StatementDigest, StatementSink};
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Proof, ProveOptions};
+use crate::zk_protocol::pi_hidden_protocol::{
+    bind_stacked, prove_stacked, stacked_challenges, verify_stacked, Pi_Hidden_Output_Proof,
+};
+use crate::zk_protocol::product_phase::{high_products, ProductEvaluations};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Zero-knowledge proof that a committed `x` satisfies public quadratic forms
+/// `x^T A_j x = y_j`, with a proof of logarithmic size.
+///
+/// A challenge `rho` folds the batch into one form `A = sum_j rho^j A_j`.
+/// The prover commits to `u = A x` right after `x`, so that `x^T A x` is the
+/// sum of the `n` products `x_i u_i`. These go through the same
+/// `ProductEvaluations` argument as the multiplication gates of
+/// `Pi_Circuit_Proof`: with `f(i) = x_i`, `g(i) = u_i` for `i = 1..n` and
+/// random `f(0)`, `g(0)`, the prover also commits to `h = f g` at
+/// `0, 1, ..., 2n`. One Pi_c proof over `P + e * C_rest` then shows
+/// `u = A x`, `sum_{i=1..n} h(i) = y` and the revealed `f(c)`, `g(c)`,
+/// `h(c)`. The key needs `4n + 3` generators.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Quadratic_Proof {
+    C_rest: CompressedGroup,
+    evaluations: ProductEvaluations,
+    proof: Pi_c_Proof,
+}
+
+impl Pi_Quadratic_Proof {
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_quadratic proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        std::mem::size_of::<CompressedGroup>()
+            + 3 * std::mem::size_of::<Scalar>()
+            + self.proof.siz()
+    }
+
+    // x, u = A x, f(0), g(0) and h(0), ..., h(2n)
+    fn committed_len(n: usize) -> usize {
+        4 * n + 3
+    }
+
+    // The shapes of the matrices alone, which the prover checks before
+    // computing the y_j from them
+    fn check_matrices(n: usize, a_matrices: &[Vec<Vec<Scalar>>]) -> Result<(), ProofVerifyError> {
+        if n == 0 || a_matrices.is_empty() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "no quadratic form over a non-empty vector".to_string(),
+            ));
+        }
+        for matrix in a_matrices {
+            if matrix.len() != n {
+                return Err(ProofVerifyError::LengthMismatch {
+                    expected: n,
+                    found: matrix.len(),
+                });
+            }
+            if let Some(row) = matrix.iter().find(|row| row.len() != n) {
+                return Err(ProofVerifyError::LengthMismatch {
+                    expected: n,
+                    found: row.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_statement(
+        n: usize,
+        a_matrices: &[Vec<Vec<Scalar>>],
+        y_vec: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        Pi_Quadratic_Proof::check_matrices(n, a_matrices)?;
+        if y_vec.len() != a_matrices.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: a_matrices.len(),
+                found: y_vec.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // Binds the statement and folds the batch into (A, y)
+    fn fold_statement(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        a_matrices: &[Vec<Vec<Scalar>>],
+        y_vec: &[Scalar],
+    ) -> (Vec<Vec<Scalar>>, Scalar) {
+        let n = a_matrices[0].len();
+        // both callers check the generators cover `committed_len(n) > n` first
+        let gens_fingerprint = gens.fingerprint(0..n).unwrap();
+        transcript.append_protocol_name(Pi_Quadratic_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        transcript.append_u64(b"forms", a_matrices.len() as u64);
+        for matrix in a_matrices {
+            let mut digest = StatementDigest::new(n + 1, &gens_fingerprint, P);
+            for row in matrix {
+                digest.add_row(row);
+            }
+            transcript.append_message(b"form_digest", &digest.finalize());
+        }
+        y_vec.to_vec().append_to_transcript(b"y_vec", transcript);
+
+        let rho = transcript.challenge_scalar(b"rho_forms");
+        let weights = scalar_math::vandemonde_challenge_one(rho, a_matrices.len());
+        let mut a_folded = vec![vec![Scalar::zero(); n]; n];
+        for (matrix, weight) in a_matrices.iter().zip(weights.iter()) {
+            for (folded, row) in a_folded.iter_mut().zip(matrix.iter()) {
+                for (a, m) in folded.iter_mut().zip(row.iter()) {
+                    *a += weight * m;
+                }
+            }
+        }
+        (a_folded, scalar_math::compute_linearform(&weights, y_vec))
+    }
+
+    // The rows u = A x, sum_{i=1..n} h(i) = y and the evaluations of f, g, h
+    // at c, weighted by powers of rho, as a form on (x, e * rest) with its
+    // value
+    fn combined_form(
+        e: &Scalar,
+        rho: &Scalar,
+        c: &Scalar,
+        a_matrix: &[Vec<Scalar>],
+        y: &Scalar,
+        evaluations: &ProductEvaluations,
+    ) -> (Vec<Scalar>, Scalar) {
+        let n = a_matrix.len();
+        let (f_0, g_0, h_start) = (2 * n, 2 * n + 1, 2 * n + 2);
+        let rho_vec = scalar_math::vandemonde_challenge_one(*rho, n + 4);
+        let mut l_vec = vec![Scalar::zero(); Pi_Quadratic_Proof::committed_len(n)];
+
+        let a_t_rho =
+            scalar_math::matrix_vector_mul(&scalar_math::matrix_transpose(a_matrix), &rho_vec[..n]);
+        for i in 0..n {
+            l_vec[i] -= a_t_rho[i];
+            l_vec[n + i] += rho_vec[i];
+        }
+        for l in l_vec[h_start + 1..h_start + n + 1].iter_mut() {
+            *l += rho_vec[n];
+        }
+
+        let mut value = rho_vec[n] * y;
+
+        let f: Vec<usize> = std::iter::once(f_0).chain(0..n).collect();
+        let g: Vec<usize> = std::iter::once(g_0).chain(n..2 * n).collect();
+        let h: Vec<usize> = (h_start..h_start + 2 * n + 1).collect();
+        let rows = evaluations.rows(c, &f, &g, &h);
+        for ((coefficients, row_value), weight) in rows.iter().zip(rho_vec[n + 1..].iter()) {
+            for (position, coefficient) in coefficients {
+                l_vec[*position] += weight * coefficient;
+            }
+            value += weight * row_value;
+        }
+
+        for l in l_vec[..n].iter_mut() {
+            *l *= e;
+        }
+        (l_vec, e * value)
+    }
+
+    /// Proves `x^T A_j x = y_j` for every `j` and the opening `(x, gamma)` of
+    /// `P`, which `options` may supply. Returns the proof, `P` and the values
+    /// `y_j`.
+    pub fn prove(
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        a_matrices: &[Vec<Vec<Scalar>>],
+        options: &ProveOptions,
+    ) -> (Pi_Quadratic_Proof, CompressedGroup, Vec<Scalar>) {
+        let n = x_vec.len();
+        Pi_Quadratic_Proof::check_matrices(n, a_matrices).unwrap();
+        let y_vec: Vec<Scalar> = a_matrices
+            .iter()
+            .map(|matrix| {
+                scalar_math::compute_linearform(
+                    x_vec,
+                    &scalar_math::matrix_vector_mul(matrix, x_vec),
+                )
+            })
+            .collect();
+        assert!(gens.gens_n.n >= Pi_Quadratic_Proof::committed_len(n));
+        let P = options.commitment_or_else(|| Pi_Affine_Proof::commit(gens, x_vec, gamma));
+
+        let (a_matrix, y) =
+            Pi_Quadratic_Proof::fold_statement(gens, transcript, &P, a_matrices, &y_vec);
+        let u_vec = scalar_math::matrix_vector_mul(&a_matrix, x_vec);
+
+        let mut f_vals = vec![prover_random_tape.random_scalar(b"f_0")];
+        f_vals.extend_from_slice(x_vec);
+        let mut g_vals = vec![prover_random_tape.random_scalar(b"g_0")];
+        g_vals.extend_from_slice(&u_vec);
+
+        let mut rest = u_vec.clone();
+        rest.extend_from_slice(&[f_vals[0], g_vals[0]]);
+        rest.extend((0..=n).map(|i| f_vals[i] * g_vals[i]));
+        rest.extend(high_products(&f_vals, &g_vals));
+
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let C_rest = Pi_Hidden_Output_Proof::commit_output(gens, n, &rest, &delta);
+        bind_stacked(gens, transcript, n, rest.len(), &C_rest).unwrap();
+
+        let (c, evaluations) = ProductEvaluations::prove(transcript, &f_vals, &g_vals);
+
+        let (e, rho) = stacked_challenges(transcript).unwrap();
+        let (l_vec, value) =
+            Pi_Quadratic_Proof::combined_form(&e, &rho, &c, &a_matrix, &y, &evaluations);
+
+        let proof = prove_stacked(
+            gens,
+            transcript,
+            prover_random_tape,
+            options.mode(),
+            x_vec,
+            gamma,
+            &P,
+            &rest,
+            &delta,
+            &C_rest,
+            &e,
+            &l_vec,
+            &value,
+        );
+
+        let proof = Pi_Quadratic_Proof {
+            C_rest,
+            evaluations,
+            proof,
+        };
+        (proof, P, y_vec)
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        a_matrices: &[Vec<Vec<Scalar>>],
+        y_vec: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        Pi_Quadratic_Proof::check_statement(n, a_matrices, y_vec)?;
+        let len = Pi_Quadratic_Proof::committed_len(n);
+        if gens.gens_n.n < len {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: len,
+                available: gens.gens_n.n,
+            });
+        }
+
+        let (a_matrix, y) =
+            Pi_Quadratic_Proof::fold_statement(gens, transcript, P, a_matrices, y_vec);
+        bind_stacked(gens, transcript, n, len - n, &self.C_rest)?;
+        let c = self.evaluations.verify(transcript)?;
+        let (e, rho) = stacked_challenges(transcript)?;
+
+        let (l_vec, value) =
+            Pi_Quadratic_Proof::combined_form(&e, &rho, &c, &a_matrix, &y, &self.evaluations);
+
+        verify_stacked(
+            &self.proof,
+            gens,
+            transcript,
+            P,
+            &self.C_rest,
+            &e,
+            &l_vec,
+            &value,
+        )
+    }
+}
+
+impl ProofEncoding for Pi_Quadratic_Proof {
+    const PROOF_TYPE: u8 = 14;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.C_rest);
+        self.evaluations.encode(writer);
+        self.proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Quadratic_Proof {
+            C_rest: reader.read_point()?,
+            evaluations: ProductEvaluations::decode(reader)?,
+            proof: Pi_c_Proof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_quadratic_proof() {
+        let mut csprng: OsRng = OsRng;
+        let n = 5;
+        let gens = DotProductProofGens::new(4 * n + 3, b"test-gens");
+        let x_vec: Vec<Scalar> = (1..=n as u64).map(Scalar::from).collect();
+        let gamma = Scalar::random(&mut csprng);
+
+        // the squared norm, and a random form
+        let identity: Vec<Vec<Scalar>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        if i == j {
+                            Scalar::one()
+                        } else {
+                            Scalar::zero()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let random: Vec<Vec<Scalar>> = (0..n)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect();
+        let a_matrices = vec![identity, random];
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P, y_vec) = Pi_Quadratic_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &a_matrices,
+            &ProveOptions::default(),
+        );
+        assert_eq!(y_vec[0], Scalar::from(55u64));
+        let proof = Pi_Quadratic_Proof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &P, &a_matrices, &y_vec)
+            .is_ok());
+
+        let mut wrong_y = y_vec.clone();
+        wrong_y[1] += Scalar::one();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &P,
+                &a_matrices,
+                &wrong_y
+            )
+            .is_err());
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(
+                n,
+                &gens,
+                &mut verifier_transcript,
+                &P,
+                &a_matrices[..1],
+                &y_vec
+            ),
+            Err(ProofVerifyError::LengthMismatch { .. })
+        ));
+    }
+}
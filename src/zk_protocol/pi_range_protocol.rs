@@ -0,0 +1,537 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::{
+    Commitments, DotProductProofGens, InnerProductProofGens, MultiCommitGens,
+};
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::group::{
+    CompressedGroup, CompressedGroupExt, GroupElement, VartimeMultiscalarMul,
+};
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::curve25519::scalar_math::inner_product;
+use crate::encoding::{ProofEncoding, ProofReader, ProofWriter};
+use crate::nozk_protocol::bullet_proof::InnerProductReductionProof;
+use crate::random::RandomTape;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Proof, ProveOptions};
+use crate::zk_protocol::pi_hidden_protocol::{
+    bind_stacked, prove_stacked, stacked_challenges, verify_stacked, Pi_Hidden_Output_Proof,
+};
+use curve25519_dalek::traits::Identity;
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// Proof that selected coordinates `x_i` of a committed `x` lie in
+/// `[0, 2^k)`, so that statements proven over the same commitment hold over
+/// the integers and not only modulo the group order.
+///
+/// The bits of the selected coordinates are committed right after `x` in
+/// `C_bits`. A Pi_c proof over `P + e * C_bits` shows that every `x_i` is
+/// the weighted sum of its `k` bits, as in `Pi_Hidden_Output_Proof`. The
+/// bits are shown to be 0 or 1 as in the Bulletproofs range proof: with
+/// `a_L` the bits and `a_R = a_L - 1` committed under `H` in `A_R`, the
+/// prover shows `a_L o a_R = 0` and `a_L - a_R = 1` through challenges `y`,
+/// `z` and a blinded inner product compressed by `InnerProductReductionProof`.
+/// The key needs `n + m k` generators `G` and `m k` generators `H` for `m`
+/// selected coordinates.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pi_Range_Proof {
+    C_bits: CompressedGroup,
+    proof: Pi_c_Proof,
+    A_R: CompressedGroup,
+    S: CompressedGroup,
+    T_1: CompressedGroup,
+    T_2: CompressedGroup,
+    tau_x: Scalar,
+    mu: Scalar,
+    t_hat: Scalar,
+    ipa_proof: InnerProductReductionProof,
+}
+
+impl Pi_Range_Proof {
+    // entries are decomposed into at most this many bits
+    pub const MAX_BITS: usize = 64;
+
+    fn protocol_name() -> &'static [u8] {
+        b"zk pi_range proof"
+    }
+
+    pub fn siz(&self) -> usize {
+        5 * std::mem::size_of::<CompressedGroup>()
+            + 3 * std::mem::size_of::<Scalar>()
+            + self.proof.siz()
+            + self.ipa_proof.siz()
+    }
+
+    fn check_statement(n: usize, indices: &[usize], k: usize) -> Result<(), ProofVerifyError> {
+        if n == 0 || indices.is_empty() {
+            return Err(ProofVerifyError::MalformedStatement(
+                "no coordinates to bound".to_string(),
+            ));
+        }
+        if k == 0 || k > Pi_Range_Proof::MAX_BITS {
+            return Err(ProofVerifyError::MalformedStatement(format!(
+                "ranges of {} bits are not supported",
+                k
+            )));
+        }
+        if let Some(i) = indices.iter().find(|i| **i >= n) {
+            return Err(ProofVerifyError::MalformedStatement(format!(
+                "coordinate {} is out of a vector of length {}",
+                i, n
+            )));
+        }
+        Ok(())
+    }
+
+    fn statement_challenges(
+        dot_gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        n: usize,
+        P: &CompressedGroup,
+        C_bits: &CompressedGroup,
+        indices: &[usize],
+        k: usize,
+    ) -> Result<(Scalar, Scalar), ProofVerifyError> {
+        transcript.append_protocol_name(Pi_Range_Proof::protocol_name());
+        transcript.append_u64(b"n", n as u64);
+        transcript.append_u64(b"k", k as u64);
+        transcript.append_u64(b"m", indices.len() as u64);
+        for i in indices {
+            transcript.append_u64(b"index", *i as u64);
+        }
+        P.append_to_transcript(b"P", transcript);
+        bind_stacked(dot_gens, transcript, n, indices.len() * k, C_bits)?;
+        stacked_challenges(transcript)
+    }
+
+    // Row j reads x_{i_j} - sum_t 2^t bit_{j, t} = 0. Returns the rho-weighted
+    // sum of the rows as a form on (x, e * bits); its value is zero.
+    fn combined_form(
+        e: &Scalar,
+        rho: &Scalar,
+        n: usize,
+        indices: &[usize],
+        k: usize,
+    ) -> Vec<Scalar> {
+        let rho_vec = scalar_math::vandemonde_challenge_one(*rho, indices.len());
+        let powers_of_2 = scalar_math::vandemonde_challenge_one(Scalar::from(2u64), k);
+
+        let mut l_vec = vec![Scalar::zero(); n + indices.len() * k];
+        for (j, (i, weight)) in indices.iter().zip(rho_vec.iter()).enumerate() {
+            l_vec[*i] += e * weight;
+            for (l, p) in l_vec[n + j * k..n + (j + 1) * k]
+                .iter_mut()
+                .zip(powers_of_2.iter())
+            {
+                *l = -(weight * p);
+            }
+        }
+        l_vec
+    }
+
+    // (z - z^2) * sum(y^i), the value of <l(0), r(0)> for valid bits
+    fn delta(y_vec: &[Scalar], z: &Scalar) -> Scalar {
+        let sum_y: Scalar = y_vec.iter().sum();
+        (z - z * z) * sum_y
+    }
+
+    fn bits_of(x: &Scalar, k: usize) -> Vec<Scalar> {
+        let bytes = x.to_bytes();
+        let bit = |t: usize| (bytes[t / 8] >> (t % 8)) & 1;
+        assert!(
+            (k..256).all(|t| bit(t) == 0),
+            "entry does not fit in {} bits",
+            k
+        );
+        (0..k).map(|t| Scalar::from(bit(t) as u64)).collect()
+    }
+
+    /// Proves that `x_i` is in `[0, 2^k)` for every `i` in `indices`, for the
+    /// opening `(x, gamma)` of `P`, which `options` may supply
+    pub fn prove(
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        x_vec: &[Scalar],
+        gamma: &Scalar,
+        indices: &[usize],
+        k: usize,
+        options: &ProveOptions,
+    ) -> (Pi_Range_Proof, CompressedGroup) {
+        let n = x_vec.len();
+        Pi_Range_Proof::check_statement(n, indices, k).unwrap();
+        let num_bits = indices.len() * k;
+        assert!(gens.gens_a.n >= n + num_bits);
+        assert!(gens.gens_b.n >= num_bits);
+        let P = options.commitment_or_else(|| {
+            x_vec
+                .commit(gamma, &gens.gens_a.prefix(x_vec.len()))
+                .compress()
+        });
+
+        let a_L: Vec<Scalar> = indices
+            .iter()
+            .flat_map(|i| Pi_Range_Proof::bits_of(&x_vec[*i], k))
+            .collect();
+        let delta = prover_random_tape.random_scalar(b"delta");
+        let dot_gens = gens.dot_product_gens();
+        let C_bits = Pi_Hidden_Output_Proof::commit_output(&dot_gens, n, &a_L, &delta);
+
+        // the bits are the weighted sums, shown with a single Pi_c proof
+        let (e, rho) =
+            Pi_Range_Proof::statement_challenges(&dot_gens, transcript, n, &P, &C_bits, indices, k)
+                .unwrap();
+        let l_vec = Pi_Range_Proof::combined_form(&e, &rho, n, indices, k);
+        let proof = prove_stacked(
+            &dot_gens,
+            transcript,
+            prover_random_tape,
+            options.mode(),
+            x_vec,
+            gamma,
+            &P,
+            &a_L,
+            &delta,
+            &C_bits,
+            &e,
+            &l_vec,
+            &Scalar::zero(),
+        );
+
+        // the bits are 0 or 1
+        let gens_G = MultiCommitGens {
+            n: num_bits,
+            G: gens.gens_a.G[n..n + num_bits].to_vec(),
+            h: gens.gens_a.h,
+        };
+        let gens_H = gens.gens_b.prefix(num_bits);
+
+        let a_R: Vec<Scalar> = a_L.iter().map(|b| b - Scalar::one()).collect();
+        let alpha = prover_random_tape.random_scalar(b"alpha");
+        let A_R = a_R.commit(&alpha, &gens_H).compress();
+        let s_L = prover_random_tape.random_vector(b"s_L", num_bits);
+        let s_R = prover_random_tape.random_vector(b"s_R", num_bits);
+        let rho_S = prover_random_tape.random_scalar(b"rho_S");
+        let S = (s_L.commit(&Scalar::zero(), &gens_G) + s_R.commit(&rho_S, &gens_H)).compress();
+        A_R.append_to_transcript(b"A_R", transcript);
+        S.append_to_transcript(b"S", transcript);
+
+        let y = transcript.challenge_scalar(b"y");
+        let z = transcript.challenge_scalar(b"z");
+        let y_vec = scalar_math::vandemonde_challenge_one(y, num_bits);
+
+        // l(X) = a_L - z + s_L X and r(X) = y^N o (a_R + z + s_R X)
+        let l_0: Vec<Scalar> = a_L.iter().map(|a| a - z).collect();
+        let r_0: Vec<Scalar> = a_R
+            .iter()
+            .zip(y_vec.iter())
+            .map(|(a, y_i)| y_i * (a + z))
+            .collect();
+        let r_1: Vec<Scalar> = s_R
+            .iter()
+            .zip(y_vec.iter())
+            .map(|(s, y_i)| y_i * s)
+            .collect();
+
+        let t_1 = inner_product(&l_0, &r_1) + inner_product(&s_L, &r_0);
+        let t_2 = inner_product(&s_L, &r_1);
+        let tau_1 = prover_random_tape.random_scalar(b"tau_1");
+        let tau_2 = prover_random_tape.random_scalar(b"tau_2");
+        let T_1 = t_1.commit(&tau_1, &gens.gens_1).compress();
+        let T_2 = t_2.commit(&tau_2, &gens.gens_1).compress();
+        T_1.append_to_transcript(b"T_1", transcript);
+        T_2.append_to_transcript(b"T_2", transcript);
+
+        let x = transcript.challenge_scalar(b"x");
+        let l: Vec<Scalar> = l_0.iter().zip(s_L.iter()).map(|(a, s)| a + x * s).collect();
+        let r: Vec<Scalar> = r_0.iter().zip(r_1.iter()).map(|(a, s)| a + x * s).collect();
+        let t_hat = inner_product(&l, &r);
+        let tau_x = tau_2 * x * x + tau_1 * x;
+        let mu = delta + alpha + rho_S * x;
+
+        tau_x.append_to_transcript(b"tau_x", transcript);
+        mu.append_to_transcript(b"mu", transcript);
+        t_hat.append_to_transcript(b"t_hat", transcript);
+
+        let w = transcript.challenge_scalar(b"w");
+        let Q = w * gens.gens_1.G[0];
+
+        // r is folded against H' = y^{-N} o H
+        let mut y_inv = y_vec.clone();
+        Scalar::batch_invert(&mut y_inv);
+        let H_prime: Vec<GroupElement> = gens_H
+            .G
+            .iter()
+            .zip(y_inv.iter())
+            .map(|(H, y_inv)| y_inv * H)
+            .collect();
+        let ipa_proof =
+            InnerProductReductionProof::prove(transcript, &Q, &gens_G.G, &H_prime, &l, &r);
+
+        let proof = Pi_Range_Proof {
+            C_bits,
+            proof,
+            A_R,
+            S,
+            T_1,
+            T_2,
+            tau_x,
+            mu,
+            t_hat,
+            ipa_proof,
+        };
+        (proof, P)
+    }
+
+    pub fn verify(
+        &self,
+        n: usize,
+        gens: &InnerProductProofGens,
+        transcript: &mut Transcript,
+        P: &CompressedGroup,
+        indices: &[usize],
+        k: usize,
+    ) -> Result<(), ProofVerifyError> {
+        Pi_Range_Proof::check_statement(n, indices, k)?;
+        let num_bits = indices.len() * k;
+        let available = gens.gens_a.n.min(gens.gens_b.n + n);
+        if available < n + num_bits {
+            return Err(ProofVerifyError::GeneratorsTooSmall {
+                needed: n + num_bits,
+                available,
+            });
+        }
+
+        let dot_gens = gens.dot_product_gens();
+        let (e, rho) = Pi_Range_Proof::statement_challenges(
+            &dot_gens,
+            transcript,
+            n,
+            P,
+            &self.C_bits,
+            indices,
+            k,
+        )?;
+        let l_vec = Pi_Range_Proof::combined_form(&e, &rho, n, indices, k);
+        verify_stacked(
+            &self.proof,
+            &dot_gens,
+            transcript,
+            P,
+            &self.C_bits,
+            &e,
+            &l_vec,
+            &Scalar::zero(),
+        )?;
+
+        self.A_R.append_to_transcript(b"A_R", transcript);
+        self.S.append_to_transcript(b"S", transcript);
+        let y = transcript.challenge_scalar(b"y");
+        let z = transcript.challenge_scalar(b"z");
+        self.T_1.append_to_transcript(b"T_1", transcript);
+        self.T_2.append_to_transcript(b"T_2", transcript);
+        let x = transcript.challenge_scalar(b"x");
+        self.tau_x.append_to_transcript(b"tau_x", transcript);
+        self.mu.append_to_transcript(b"mu", transcript);
+        self.t_hat.append_to_transcript(b"t_hat", transcript);
+        let w = transcript.challenge_scalar(b"w");
+        if y == Scalar::zero() {
+            return Err(ProofVerifyError::ZeroChallenge);
+        }
+
+        let k_gen = gens.gens_1.G[0];
+        let h = gens.gens_1.h;
+        let y_vec = scalar_math::vandemonde_challenge_one(y, num_bits);
+
+        // t_hat * k + tau_x * h == delta(y, z) * k + x * T_1 + x^2 * T_2
+        let value_check = GroupElement::vartime_multiscalar_mul(
+            &[
+                self.t_hat - Pi_Range_Proof::delta(&y_vec, &z),
+                self.tau_x,
+                -x,
+                -(x * x),
+            ],
+            &[k_gen, h, self.T_1.unpack()?, self.T_2.unpack()?],
+        );
+        if value_check != GroupElement::identity() {
+            return Err(ProofVerifyError::FinalCheckFailed);
+        }
+
+        // C_bits + A_R + x * S - z <1, G> + z <1, H> - mu * h equals
+        // <l, G> + <r, H'>, and joins the folded relation with t_hat * w * k
+        let (G_scalars, H_scalars, Q_scalar, mut scalars, mut points) =
+            self.ipa_proof.verification_terms(num_bits, transcript)?;
+        let mut y_inv = y_vec;
+        Scalar::batch_invert(&mut y_inv);
+        let G_scalars = G_scalars.iter().map(|s| s - z);
+        let H_scalars = H_scalars
+            .iter()
+            .zip(y_inv.iter())
+            .map(|(s, y_inv)| s * y_inv + z);
+
+        scalars.extend_from_slice(&[
+            Scalar::one(),
+            Scalar::one(),
+            x,
+            -self.mu,
+            (self.t_hat + Q_scalar) * w,
+        ]);
+        points.extend_from_slice(&[
+            self.C_bits.unpack()?,
+            self.A_R.unpack()?,
+            self.S.unpack()?,
+            h,
+            k_gen,
+        ]);
+
+        let result = GroupElement::vartime_multiscalar_mul(
+            G_scalars.chain(H_scalars).chain(scalars),
+            gens.gens_a.G[n..n + num_bits]
+                .iter()
+                .chain(gens.gens_b.G[..num_bits].iter())
+                .chain(points.iter()),
+        );
+
+        if result == GroupElement::identity() {
+            Ok(())
+        } else {
+            Err(ProofVerifyError::FinalCheckFailed)
+        }
+    }
+}
+
+impl ProofEncoding for Pi_Range_Proof {
+    const PROOF_TYPE: u8 = 12;
+
+    fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_point(&self.C_bits);
+        self.proof.encode(writer);
+        writer.write_point(&self.A_R);
+        writer.write_point(&self.S);
+        writer.write_point(&self.T_1);
+        writer.write_point(&self.T_2);
+        writer.write_scalar(&self.tau_x);
+        writer.write_scalar(&self.mu);
+        writer.write_scalar(&self.t_hat);
+        self.ipa_proof.encode(writer);
+    }
+
+    fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(Pi_Range_Proof {
+            C_bits: reader.read_point()?,
+            proof: Pi_c_Proof::decode(reader)?,
+            A_R: reader.read_point()?,
+            S: reader.read_point()?,
+            T_1: reader.read_point()?,
+            T_2: reader.read_point()?,
+            tau_x: reader.read_scalar()?,
+            mu: reader.read_scalar()?,
+            t_hat: reader.read_scalar()?,
+            ipa_proof: InnerProductReductionProof::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_pi_range_proof() {
+        let mut csprng: OsRng = OsRng;
+        let n = 6;
+        let k = 30;
+        let indices = [0, 2, 5];
+        let gens = InnerProductProofGens::new(n + indices.len() * k, b"test-gens");
+
+        let x_vec: Vec<Scalar> = (0..n as u64)
+            .map(|i| Scalar::from(999_999_937 - i * 1_000))
+            .collect();
+        let gamma = Scalar::random(&mut csprng);
+
+        // the range proof and an affine proof over the same commitment
+        let m_matric: Vec<Vec<Scalar>> = (0..2)
+            .map(|_| (0..n).map(|_| Scalar::random(&mut csprng)).collect())
+            .collect();
+        let b_vec = scalar_math::matrix_vector_mul(&m_matric, &x_vec);
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, P) = Pi_Range_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &indices,
+            k,
+            &ProveOptions::default(),
+        );
+        let (affine_proof, _) = Pi_Affine_Proof::prove_affine(
+            &gens.dot_product_gens(),
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &gamma,
+            &m_matric,
+            &b_vec,
+            &ProveOptions::default().with_commitment(&P),
+        );
+        let proof = Pi_Range_Proof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &P, &indices, k)
+            .is_ok());
+        assert!(affine_proof
+            .verify_affine(
+                n,
+                &gens.dot_product_gens(),
+                &mut verifier_transcript,
+                &m_matric,
+                &b_vec,
+                &P
+            )
+            .is_ok());
+
+        // a narrower range or other coordinates do not verify
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &P, &indices, k - 1)
+            .is_err());
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(proof
+            .verify(n, &gens, &mut verifier_transcript, &P, &[0, 2, 4], k)
+            .is_err());
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(matches!(
+            proof.verify(n, &gens, &mut verifier_transcript, &P, &[0, 6], k),
+            Err(ProofVerifyError::MalformedStatement(_))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "entry does not fit in 30 bits")]
+    fn check_pi_range_proof_rejects_large_entries() {
+        let gens = InnerProductProofGens::new(2 + 30, b"test-gens");
+        let x_vec = vec![Scalar::from(1u64 << 30), Scalar::one()];
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        Pi_Range_Proof::prove(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &x_vec,
+            &Scalar::one(),
+            &[0],
+            30,
+            &ProveOptions::default(),
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Polynomial commitments with evaluation proofs.
+//!
+//! A polynomial `p(X) = p_0 + p_1 X + ... + p_{n-1} X^{n-1}` is committed as
+//! the Pedersen commitment to its coefficients. Its value at `z` is the
+//! linear form `(1, z, ..., z^{n-1})` of the coefficients, so openings at
+//! several points are the rows of a Vandermonde matrix `V` and are proven
+//! together with one Pi_c proof:
+//!
+//! * public values `v = V p` with `Pi_Affine_Proof::prove_affine`,
+//! * committed values with `Pi_Hidden_Output_Proof`, the values being
+//!   committed right after the coefficients (`commit_values`).
+//!
+//! The commitment is a `pedersen::Commitment`, so other proofs over the same
+//! coefficients take `PolyCommitment::compress()` as their `P`.
+#![allow(non_snake_case)]
+#![allow(clippy::too_many_arguments)]
+use crate::commitments::pedersen::{Commitment, Opening};
+use crate::commitments::DotProductProofGens;
+use crate::curve25519::errors::ProofVerifyError;
+use crate::curve25519::group::CompressedGroup;
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::random::RandomTape;
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use crate::zk_protocol::pi_a_protocol::Pi_Affine_Proof;
+use crate::zk_protocol::pi_c_protocol::{Pi_c_Mode, ProveOptions};
+use crate::zk_protocol::pi_hidden_protocol::Pi_Hidden_Output_Proof;
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolyCommitment {
+    num_coeffs: usize,
+    commitment: Commitment,
+}
+
+impl PolyCommitment {
+    fn protocol_name() -> &'static [u8] {
+        b"zk poly commitment evaluation"
+    }
+
+    /// Commits to the coefficients `opening.x_vec`, lowest degree first
+    pub fn commit(gens: &DotProductProofGens, opening: &Opening) -> PolyCommitment {
+        assert!(!opening.x_vec.is_empty());
+        PolyCommitment {
+            num_coeffs: opening.x_vec.len(),
+            commitment: opening.commit(&gens.gens_n),
+        }
+    }
+
+    /// A commitment to a polynomial with `num_coeffs` coefficients received
+    /// from the prover
+    pub fn from_compressed(
+        num_coeffs: usize,
+        P: &CompressedGroup,
+    ) -> Result<PolyCommitment, ProofVerifyError> {
+        Ok(PolyCommitment {
+            num_coeffs,
+            commitment: Commitment::from_compressed(P)?,
+        })
+    }
+
+    pub fn num_coeffs(&self) -> usize {
+        self.num_coeffs
+    }
+
+    pub fn commitment(&self) -> &Commitment {
+        &self.commitment
+    }
+
+    pub fn compress(&self) -> CompressedGroup {
+        self.commitment.compress()
+    }
+
+    pub fn evaluate(coeffs: &[Scalar], z: &Scalar) -> Scalar {
+        // Horner's rule
+        coeffs
+            .iter()
+            .rev()
+            .fold(Scalar::zero(), |acc, coeff| acc * z + coeff)
+    }
+
+    // One row (1, z, ..., z^{n-1}) per point
+    fn evaluation_matrix(&self, points: &[Scalar]) -> Vec<Vec<Scalar>> {
+        points
+            .iter()
+            .map(|z| scalar_math::vandemonde_challenge_one(*z, self.num_coeffs))
+            .collect()
+    }
+
+    fn append_points(&self, transcript: &mut Transcript, points: &[Scalar]) {
+        transcript.append_protocol_name(PolyCommitment::protocol_name());
+        transcript.append_u64(b"num_coeffs", self.num_coeffs as u64);
+        points.to_vec().append_to_transcript(b"points", transcript);
+    }
+
+    /// The commitment to the values at the points, as taken by
+    /// `verify_committed_evals`: the values sit after the coefficients
+    pub fn commit_values(
+        &self,
+        gens: &DotProductProofGens,
+        values: &[Scalar],
+        delta: &Scalar,
+    ) -> CompressedGroup {
+        Pi_Hidden_Output_Proof::commit_output(gens, self.num_coeffs, values, delta)
+    }
+
+    /// Proves `p(z_j) = v_j` at every point and returns the public values.
+    /// The proof is always against this commitment, so of the `ProveOptions`
+    /// of other proofs only the mode is taken.
+    pub fn prove_evals(
+        &self,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        opening: &Opening,
+        points: &[Scalar],
+        mode: Pi_c_Mode,
+    ) -> (Pi_Affine_Proof, Vec<Scalar>) {
+        assert_eq!(opening.x_vec.len(), self.num_coeffs);
+        assert!(!points.is_empty());
+        let values: Vec<Scalar> = points
+            .iter()
+            .map(|z| PolyCommitment::evaluate(&opening.x_vec, z))
+            .collect();
+
+        self.append_points(transcript, points);
+        let (proof, _) = Pi_Affine_Proof::prove_affine(
+            gens,
+            transcript,
+            prover_random_tape,
+            &opening.x_vec,
+            &opening.gamma,
+            &self.evaluation_matrix(points),
+            &values,
+            &ProveOptions::default()
+                .with_mode(mode)
+                .with_commitment(&self.compress()),
+        );
+        (proof, values)
+    }
+
+    pub fn verify_evals(
+        &self,
+        proof: &Pi_Affine_Proof,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        points: &[Scalar],
+        values: &[Scalar],
+    ) -> Result<(), ProofVerifyError> {
+        if points.len() != values.len() {
+            return Err(ProofVerifyError::LengthMismatch {
+                expected: points.len(),
+                found: values.len(),
+            });
+        }
+        self.append_points(transcript, points);
+        proof.verify_affine(
+            self.num_coeffs,
+            gens,
+            transcript,
+            &self.evaluation_matrix(points),
+            values,
+            &self.compress(),
+        )
+    }
+
+    /// Proves `p(z_j) = v_j` at every point for values committed with
+    /// `commit_values` and the blinding `delta`. Returns the proof, the
+    /// commitment to the values and the values themselves for the prover.
+    pub fn prove_committed_evals(
+        &self,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        prover_random_tape: &mut RandomTape,
+        opening: &Opening,
+        points: &[Scalar],
+        delta: &Scalar,
+        mode: Pi_c_Mode,
+    ) -> (Pi_Hidden_Output_Proof, CompressedGroup, Vec<Scalar>) {
+        assert_eq!(opening.x_vec.len(), self.num_coeffs);
+        assert!(!points.is_empty());
+        let values: Vec<Scalar> = points
+            .iter()
+            .map(|z| PolyCommitment::evaluate(&opening.x_vec, z))
+            .collect();
+        let C_v = self.commit_values(gens, &values, delta);
+
+        self.append_points(transcript, points);
+        let (proof, _) = Pi_Hidden_Output_Proof::prove_with_output(
+            gens,
+            transcript,
+            prover_random_tape,
+            &opening.x_vec,
+            &opening.gamma,
+            delta,
+            &C_v,
+            &self.evaluation_matrix(points),
+            &ProveOptions::default()
+                .with_mode(mode)
+                .with_commitment(&self.compress()),
+        );
+        (proof, C_v, values)
+    }
+
+    pub fn verify_committed_evals(
+        &self,
+        proof: &Pi_Hidden_Output_Proof,
+        gens: &DotProductProofGens,
+        transcript: &mut Transcript,
+        points: &[Scalar],
+        C_v: &CompressedGroup,
+    ) -> Result<(), ProofVerifyError> {
+        self.append_points(transcript, points);
+        proof.verify(
+            self.num_coeffs,
+            gens,
+            transcript,
+            &self.evaluation_matrix(points),
+            &self.compress(),
+            C_v,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn check_poly_commitment_evals() {
+        let mut csprng: OsRng = OsRng;
+        let n = 9;
+        let gens = DotProductProofGens::new(n + 3, b"test-gens");
+        let coeffs: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut csprng)).collect();
+        let opening = Opening::random(coeffs, &mut csprng);
+        let poly = PolyCommitment::commit(&gens, &opening);
+        let points: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut csprng)).collect();
+
+        // p(2) for p = 1 + 2X + 3X^2
+        assert_eq!(
+            PolyCommitment::evaluate(
+                &[Scalar::one(), Scalar::from(2u64), Scalar::from(3u64)],
+                &Scalar::from(2u64)
+            ),
+            Scalar::from(17u64)
+        );
+
+        let mut prover_random_tape = RandomTape::new(b"proof");
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, values) = poly.prove_evals(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &opening,
+            &points,
+            Pi_c_Mode::Compressed,
+        );
+
+        let received = PolyCommitment::from_compressed(n, &poly.compress()).unwrap();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(received
+            .verify_evals(&proof, &gens, &mut verifier_transcript, &points, &values)
+            .is_ok());
+
+        let mut wrong_values = values.clone();
+        wrong_values[2] += Scalar::one();
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(received
+            .verify_evals(
+                &proof,
+                &gens,
+                &mut verifier_transcript,
+                &points,
+                &wrong_values
+            )
+            .is_err());
+
+        // the same openings with committed values
+        let delta = Scalar::random(&mut csprng);
+        let mut prover_transcript = Transcript::new(b"example");
+        let (proof, C_v, committed_values) = poly.prove_committed_evals(
+            &gens,
+            &mut prover_transcript,
+            &mut prover_random_tape,
+            &opening,
+            &points,
+            &delta,
+            Pi_c_Mode::Full,
+        );
+        assert_eq!(committed_values, values);
+        assert_eq!(proof.mode(), Pi_c_Mode::Full);
+
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(received
+            .verify_committed_evals(&proof, &gens, &mut verifier_transcript, &points, &C_v)
+            .is_ok());
+
+        let wrong_C_v = poly.commit_values(&gens, &wrong_values, &delta);
+        let mut verifier_transcript = Transcript::new(b"example");
+        assert!(received
+            .verify_committed_evals(&proof, &gens, &mut verifier_transcript, &points, &wrong_C_v)
+            .is_err());
+    }
+}
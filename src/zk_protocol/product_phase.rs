@@ -0,0 +1,133 @@
+use crate::curve25519::errors::{ProofDecodeError, ProofVerifyError};
+use crate::curve25519::scalar::Scalar;
+use crate::curve25519::scalar_math;
+use crate::encoding::{ProofReader, ProofWriter};
+use crate::transcript::{AppendToTranscript, ProofTranscript};
+use merlin::Transcript;
+use serde::{Deserialize, Serialize};
+
+/// The multiplication-gate argument of Section 5 of the paper, shared by
+/// `Pi_Circuit_Proof` and `Pi_Quadratic_Proof`. For `m` products
+/// `alpha_i * beta_i = gamma_i`, `f` and `g` of degree `m` take `f(i) = alpha_i`,
+/// `g(i) = beta_i` and random `f(0)`, `g(0)`, and `h = f g` has degree `2m`.
+/// The values of `f` at `0..=m`, `g` at `0..=m` and `h` at `0..=2m` all sit
+/// in the committed vector, so for a challenge `c` the evaluations `f(c)`,
+/// `g(c)`, `h(c)` are linear forms of it. The prover reveals them, the Pi_c
+/// proof shows them with the rest of the statement (`rows`), and the
+/// verifier checks `f(c) g(c) = h(c)`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductEvaluations {
+    f_c: Scalar,
+    g_c: Scalar,
+    h_c: Scalar,
+}
+
+// The value at `at` of the polynomial of degree `values.len() - 1` taking
+// `values` at 0, 1, ...
+fn evaluate(values: &[Scalar], at: Scalar) -> Scalar {
+    scalar_math::inner_product(
+        &scalar_math::lagrange_coefficients(at, values.len() - 1),
+        values,
+    )
+}
+
+/// `h(m + 1), ..., h(2m)` for `f`, `g` given by their values at `0..=m`; with
+/// `h(0..=m)` they determine `h`
+pub fn high_products(f_vals: &[Scalar], g_vals: &[Scalar]) -> Vec<Scalar> {
+    assert_eq!(f_vals.len(), g_vals.len());
+    let m = f_vals.len() as u64 - 1;
+    (m + 1..=2 * m)
+        .map(|at| {
+            let at = Scalar::from(at);
+            evaluate(f_vals, at) * evaluate(g_vals, at)
+        })
+        .collect()
+}
+
+impl ProductEvaluations {
+    /// Draws `c` and reveals `f(c)`, `g(c)` and `h(c) = f(c) g(c)`. Without
+    /// products (`f_vals` empty) all three are zero.
+    pub fn prove(
+        transcript: &mut Transcript,
+        f_vals: &[Scalar],
+        g_vals: &[Scalar],
+    ) -> (Scalar, ProductEvaluations) {
+        let c = transcript.challenge_scalar(b"c");
+        let (f_c, g_c) = if f_vals.is_empty() {
+            (Scalar::zero(), Scalar::zero())
+        } else {
+            (evaluate(f_vals, c), evaluate(g_vals, c))
+        };
+        let evaluations = ProductEvaluations {
+            f_c,
+            g_c,
+            h_c: f_c * g_c,
+        };
+        evaluations.append_to_transcript(transcript);
+        (c, evaluations)
+    }
+
+    /// Draws `c` as `prove` does and checks `f(c) g(c) = h(c)`
+    pub fn verify(&self, transcript: &mut Transcript) -> Result<Scalar, ProofVerifyError> {
+        let c = transcript.challenge_scalar(b"c");
+        self.append_to_transcript(transcript);
+        if self.f_c * self.g_c != self.h_c {
+            return Err(ProofVerifyError::FinalCheckFailed);
+        }
+        Ok(c)
+    }
+
+    fn append_to_transcript(&self, transcript: &mut Transcript) {
+        self.f_c.append_to_transcript(b"f_c", transcript);
+        self.g_c.append_to_transcript(b"g_c", transcript);
+        self.h_c.append_to_transcript(b"h_c", transcript);
+    }
+
+    /// The rows `f(c) = f_c`, `g(c) = g_c` and `h(c) = h_c` as (coefficients
+    /// by position, value), given the positions of `f(0..=m)`, `g(0..=m)` and
+    /// `h(0..=2m)` in the committed vector
+    pub fn rows(
+        &self,
+        c: &Scalar,
+        f_positions: &[usize],
+        g_positions: &[usize],
+        h_positions: &[usize],
+    ) -> Vec<(Vec<(usize, Scalar)>, Scalar)> {
+        let m = f_positions.len() - 1;
+        assert_eq!(g_positions.len(), m + 1);
+        assert_eq!(h_positions.len(), 2 * m + 1);
+        let basis_m = scalar_math::lagrange_coefficients(*c, m);
+        let basis_2m = scalar_math::lagrange_coefficients(*c, 2 * m);
+        [
+            (f_positions, &basis_m, self.f_c),
+            (g_positions, &basis_m, self.g_c),
+            (h_positions, &basis_2m, self.h_c),
+        ]
+        .into_iter()
+        .map(|(positions, basis, value)| {
+            (
+                positions
+                    .iter()
+                    .copied()
+                    .zip(basis.iter().copied())
+                    .collect(),
+                value,
+            )
+        })
+        .collect()
+    }
+
+    pub fn encode(&self, writer: &mut ProofWriter) {
+        writer.write_scalar(&self.f_c);
+        writer.write_scalar(&self.g_c);
+        writer.write_scalar(&self.h_c);
+    }
+
+    pub fn decode(reader: &mut ProofReader) -> Result<Self, ProofDecodeError> {
+        Ok(ProductEvaluations {
+            f_c: reader.read_scalar()?,
+            g_c: reader.read_scalar()?,
+            h_c: reader.read_scalar()?,
+        })
+    }
+}
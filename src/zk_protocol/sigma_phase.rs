@@ -15,21 +15,32 @@ pub fn commit_phase(
     let n = l_form_vec.len();
     assert_eq!(gens_n.n, n);
 
+    let (A, r_vec, rho) = opening_commit_phase(transcript, prover_random_tape, gens_n);
+    let t = scalar_math::compute_linearform(l_form_vec, &r_vec);
+
+    t.append_to_transcript(b"t", transcript);
+
+    (A, t, r_vec, rho)
+}
+
+// The first message when there is no linear form: A = <r, G> + rho * h only
+pub fn opening_commit_phase(
+    transcript: &mut Transcript,
+    prover_random_tape: &mut RandomTape,
+    gens_n: &MultiCommitGens,
+) -> (CompressedGroup, Vec<Scalar>, Scalar) {
     // produce r, rho as random factor
-    let r_vec = prover_random_tape.random_vector(b"r_vec", n);
+    let r_vec = prover_random_tape.random_vector(b"r_vec", gens_n.n);
     let rho = prover_random_tape.random_scalar(b"rho");
     let A = r_vec.commit(&rho, gens_n).compress();
-    let t = scalar_math::compute_linearform(&l_form_vec, &r_vec);
 
     A.append_to_transcript(b"A", transcript);
-    t.append_to_transcript(b"t", transcript);
 
-    (A, t, r_vec, rho)
+    (A, r_vec, rho)
 }
 
 pub fn challenge_phase(transcript: &mut Transcript) -> Scalar {
-    let c = transcript.challenge_scalar(b"c");
-    c
+    transcript.challenge_scalar(b"c")
 }
 
 pub fn response_phase(